@@ -0,0 +1,228 @@
+// src/arbitration.rs
+
+use std::collections::{HashMap, VecDeque};
+use std::time::{Duration, Instant};
+
+use crate::encoding::BroadcastMessage;
+
+// 冗余线路 A/B
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Line {
+    A,
+    B,
+}
+
+impl Line {
+    fn index(self) -> usize {
+        match self {
+            Line::A => 0,
+            Line::B => 1,
+        }
+    }
+
+    fn other(self) -> Line {
+        match self {
+            Line::A => Line::B,
+            Line::B => Line::A,
+        }
+    }
+}
+
+// 判定两条线路上是同一条消息的键
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+enum ArbitrationKey {
+    // (instance_tag, product_id, buy_order_id, sell_order_id, price, quantity)
+    Trade([u8; 8], u16, u64, u64, u64, u32),
+    // (instance_tag, product_id, bids, asks, matched, received, start_time)
+    Status([u8; 8], u16, u32, u32, u32, u32, u64),
+}
+
+impl ArbitrationKey {
+    fn of(message: &BroadcastMessage) -> Self {
+        match message {
+            BroadcastMessage::Trade(r) => ArbitrationKey::Trade(r.instance_tag, r.product_id,
+                r.buy_order_id, r.sell_order_id, r.price, r.quantity),
+            BroadcastMessage::Status(s) => ArbitrationKey::Status(s.instance_tag, s.product_id,
+                s.bids_size, s.ask_size, s.matched_orders, s.total_received_orders, s.start_time),
+        }
+    }
+}
+
+#[derive(Debug)]
+struct PendingMessage {
+    first_line: Line,
+    first_at: Instant,
+    seen: [bool; 2],
+}
+
+#[derive(Debug, Default, Clone)]
+struct LineStats {
+    received: u64,
+    wins: u64,                 // 两条线路都收到时，本线路先到达的次数
+    lost: u64,                 // 只在另一条线路上出现的消息数
+    total_advantage: Duration, // 先到达时领先另一条线路的累计时间
+    max_advantage: Duration,
+}
+
+// A/B 线路仲裁：同一消息只交付先到达的一份，统计各线路的领先时间和丢包
+pub struct Arbiter {
+    groups: [usize; 2], // 线路 A/B 在 GroupListener 中的下标
+    window: Duration,   // 等待另一条线路副本的时间，超过即判定丢包
+    pending: HashMap<ArbitrationKey, PendingMessage>,
+    expiry: VecDeque<(Instant, ArbitrationKey)>,
+    stats: [LineStats; 2],
+}
+
+impl Arbiter {
+    pub fn new(group_a: usize, group_b: usize, window: Duration) -> Self {
+        Arbiter {
+            groups: [group_a, group_b],
+            window,
+            pending: HashMap::new(),
+            expiry: VecDeque::new(),
+            stats: [LineStats::default(), LineStats::default()],
+        }
+    }
+
+    fn line_of(&self, group: usize) -> Option<Line> {
+        if group == self.groups[0] {
+            Some(Line::A)
+        } else if group == self.groups[1] {
+            Some(Line::B)
+        } else {
+            None
+        }
+    }
+
+    // 返回 true 表示应交付该消息 (首次到达或不属于 A/B 线路)
+    pub fn accept(&mut self, group: usize, message: &BroadcastMessage, received_at: Instant) -> bool {
+        let Some(line) = self.line_of(group) else {
+            return true;
+        };
+        self.expire(received_at);
+        self.stats[line.index()].received += 1;
+
+        let key = ArbitrationKey::of(message);
+        match self.pending.get_mut(&key) {
+            Some(pending) if !pending.seen[line.index()] => {
+                // 另一条线路的副本：记录先到线路的领先时间，不再交付
+                pending.seen[line.index()] = true;
+                let advantage = received_at.saturating_duration_since(pending.first_at);
+                let winner = &mut self.stats[pending.first_line.index()];
+                winner.wins += 1;
+                winner.total_advantage += advantage;
+                winner.max_advantage = winner.max_advantage.max(advantage);
+                false
+            }
+            _ => {
+                // 首次到达 (或同一线路上的重复发送，视为新消息)
+                let mut seen = [false; 2];
+                seen[line.index()] = true;
+                self.pending.insert(key.clone(), PendingMessage { first_line: line, first_at: received_at, seen });
+                self.expiry.push_back((received_at, key));
+                true
+            }
+        }
+    }
+
+    // 超过仲裁窗口仍只在一条线路出现的消息，计为另一条线路丢包
+    pub fn expire(&mut self, now: Instant) {
+        while let Some((first_at, _)) = self.expiry.front() {
+            if now.saturating_duration_since(*first_at) < self.window {
+                break;
+            }
+            let Some((first_at, key)) = self.expiry.pop_front() else {
+                break;
+            };
+            // 同一键可能已被更新的记录替换，只处理时间一致的那条
+            if let Some(pending) = self.pending.get(&key)
+                && pending.first_at == first_at {
+                if !pending.seen[pending.first_line.other().index()] {
+                    self.stats[pending.first_line.other().index()].lost += 1;
+                }
+                self.pending.remove(&key);
+            }
+        }
+    }
+
+    pub fn print(&self) {
+        println!("A/B Arbitration (window {}ms):", self.window.as_millis());
+        for line in [Line::A, Line::B] {
+            let stats = &self.stats[line.index()];
+            let avg_advantage = if stats.wins > 0 { stats.total_advantage / stats.wins as u32 } else { Duration::ZERO };
+            println!("  Line {:?}: Received={} | Leads={} | AvgLead={}us | MaxLead={}us | Lost={}",
+                line, stats.received, stats.wins, avg_advantage.as_micros(), stats.max_advantage.as_micros(), stats.lost);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::MatchResult;
+
+    const GROUP_A: usize = 0;
+    const GROUP_B: usize = 1;
+    const WINDOW: Duration = Duration::from_millis(100);
+
+    fn trade(buy_order_id: u64) -> BroadcastMessage {
+        BroadcastMessage::Trade(MatchResult {
+            instance_tag: *b"ENGINE-A",
+            product_id: 1,
+            buy_order_id,
+            sell_order_id: 2,
+            price: 100,
+            quantity: 10,
+            trade_network_time: 0,
+            internal_match_time: 0,
+            sequence: 0,
+        })
+    }
+
+    #[test]
+    fn second_copy_is_dropped_and_leader_credited() {
+        let mut arbiter = Arbiter::new(GROUP_A, GROUP_B, WINDOW);
+        let start = Instant::now();
+        assert!(arbiter.accept(GROUP_B, &trade(1), start));
+        assert!(!arbiter.accept(GROUP_A, &trade(1), start + Duration::from_micros(250)));
+
+        let b = &arbiter.stats[Line::B.index()];
+        assert_eq!((b.received, b.wins, b.lost), (1, 1, 0));
+        assert_eq!(b.max_advantage, Duration::from_micros(250));
+        assert_eq!(arbiter.stats[Line::A.index()].wins, 0);
+    }
+
+    #[test]
+    fn repeat_on_same_line_is_a_new_message() {
+        let mut arbiter = Arbiter::new(GROUP_A, GROUP_B, WINDOW);
+        let start = Instant::now();
+        assert!(arbiter.accept(GROUP_A, &trade(1), start));
+        assert!(arbiter.accept(GROUP_A, &trade(1), start));
+        // 不同内容的消息互不影响
+        assert!(arbiter.accept(GROUP_B, &trade(2), start));
+        assert!(!arbiter.accept(GROUP_B, &trade(1), start));
+    }
+
+    #[test]
+    fn missing_copy_counts_as_loss_after_window() {
+        let mut arbiter = Arbiter::new(GROUP_A, GROUP_B, WINDOW);
+        let start = Instant::now();
+        assert!(arbiter.accept(GROUP_A, &trade(1), start));
+        arbiter.expire(start + WINDOW / 2);
+        assert_eq!(arbiter.stats[Line::B.index()].lost, 0);
+        arbiter.expire(start + WINDOW);
+        assert_eq!(arbiter.stats[Line::B.index()].lost, 1);
+
+        // 窗口外到达的副本按新消息交付
+        assert!(arbiter.accept(GROUP_B, &trade(1), start + WINDOW));
+    }
+
+    #[test]
+    fn other_groups_bypass_arbitration() {
+        let mut arbiter = Arbiter::new(GROUP_A, GROUP_B, WINDOW);
+        let start = Instant::now();
+        assert!(arbiter.accept(2, &trade(1), start));
+        assert!(arbiter.accept(2, &trade(1), start));
+        assert_eq!(arbiter.stats[Line::A.index()].received + arbiter.stats[Line::B.index()].received, 0);
+    }
+}
@@ -0,0 +1,145 @@
+// src/clock.rs
+
+use std::fmt;
+
+use crate::tif::parse_rfc3339_nanos;
+use crate::types::get_nanos_since_epoch;
+
+// 下单路径使用的时钟：submit_time、默认订单 ID、风控和订单日志的时间都取自这里
+//   system              系统时间
+//   fixed:<T>           每次读取都返回 T
+//   offset:<NS>         系统时间加偏移 (纳秒，可为负)
+//   simulated:<T>[:NS]  从 T 开始，每次读取后前进 NS 纳秒 (默认 1000)
+// T 为 Unix 纳秒或 RFC 3339 时间；fixed/simulated 可以生成逐字节相同的帧
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Clock {
+    System,
+    Fixed(u64),
+    Offset(i64),
+    Simulated { next: u64, step: u64 },
+}
+
+const DEFAULT_SIMULATED_STEP: u64 = 1000;
+
+impl Clock {
+    pub fn now(&mut self) -> Result<u64, String> {
+        match self {
+            Clock::System => get_nanos_since_epoch(),
+            Clock::Fixed(time) => Ok(*time),
+            Clock::Offset(offset) => get_nanos_since_epoch()?
+                .checked_add_signed(*offset)
+                .ok_or_else(|| format!("Clock offset {} out of range", offset)),
+            Clock::Simulated { next, step } => {
+                let now = *next;
+                *next = next.checked_add(*step).ok_or_else(|| "Simulated clock overflow".to_string())?;
+                Ok(now)
+            }
+        }
+    }
+}
+
+impl fmt::Display for Clock {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Clock::System => write!(f, "system"),
+            Clock::Fixed(time) => write!(f, "fixed:{}", time),
+            Clock::Offset(offset) => write!(f, "offset:{}", offset),
+            Clock::Simulated { next, step } => write!(f, "simulated:{}:{}", next, step),
+        }
+    }
+}
+
+// Unix 纳秒或 RFC 3339 时间
+pub fn parse_timestamp(s: &str) -> Result<u64, String> {
+    match s.parse::<u64>() {
+        Ok(nanos) => Ok(nanos),
+        Err(_) => parse_rfc3339_nanos(s),
+    }
+}
+
+pub fn parse_clock(s: &str) -> Result<Clock, String> {
+    let (kind, value) = s.split_once(':').unwrap_or((s, ""));
+    match kind.to_lowercase().as_str() {
+        "system" if value.is_empty() => Ok(Clock::System),
+        "fixed" => Ok(Clock::Fixed(parse_timestamp(value)?)),
+        "offset" => value.parse::<i64>()
+            .map(Clock::Offset)
+            .map_err(|_| format!("Invalid clock offset: {}. Expected signed nanoseconds", value)),
+        "simulated" => {
+            // RFC 3339 时间本身含冒号，步长只从最后一个冒号后的纯数字中取
+            let parsed = value.rsplit_once(':')
+                .and_then(|(start, step)| Some((parse_timestamp(start).ok()?, step.parse::<u64>().ok()?)));
+            let (next, step) = match parsed {
+                Some(parsed) => parsed,
+                None => (parse_timestamp(value)?, DEFAULT_SIMULATED_STEP),
+            };
+            Ok(Clock::Simulated { next, step })
+        }
+        _ => Err(format!("Invalid clock: {}. Expected system, fixed:<T>, offset:<NS> or simulated:<T>[:<NS>]", s)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const T: u64 = 1_760_776_200_000_000_000; // 2025-10-18T08:30:00Z
+
+    #[test]
+    fn parses_each_clock_spec() {
+        assert_eq!(parse_clock("system"), Ok(Clock::System));
+        assert_eq!(parse_clock("SYSTEM"), Ok(Clock::System));
+        assert_eq!(parse_clock(&format!("fixed:{}", T)), Ok(Clock::Fixed(T)));
+        assert_eq!(parse_clock("fixed:2025-10-18T16:30:00+08:00"), Ok(Clock::Fixed(T)));
+        assert_eq!(parse_clock("offset:-1500"), Ok(Clock::Offset(-1500)));
+        assert_eq!(parse_clock("offset:+20"), Ok(Clock::Offset(20)));
+    }
+
+    #[test]
+    fn simulated_step_is_optional() {
+        let simulated = |next, step| Ok(Clock::Simulated { next, step });
+        assert_eq!(parse_clock(&format!("simulated:{}", T)), simulated(T, DEFAULT_SIMULATED_STEP));
+        assert_eq!(parse_clock(&format!("simulated:{}:5", T)), simulated(T, 5));
+        // RFC 3339 时间中的冒号不会被当作步长
+        assert_eq!(parse_clock("simulated:2025-10-18T08:30:00Z"), simulated(T, DEFAULT_SIMULATED_STEP));
+        assert_eq!(parse_clock("simulated:2025-10-18T16:30:00+08:00"), simulated(T, DEFAULT_SIMULATED_STEP));
+        assert_eq!(parse_clock("simulated:2025-10-18T16:30:00+08:00:250"), simulated(T, 250));
+    }
+
+    #[test]
+    fn rejects_malformed_specs() {
+        for spec in ["", "system:1", "fixed", "fixed:", "fixed:soon", "offset", "offset:1.5", "offset:9223372036854775808",
+            "simulated", "simulated:1000:", "simulated:1000:-1", "wall", "fixed:-1"] {
+            assert!(parse_clock(spec).is_err(), "{} should be rejected", spec);
+        }
+        assert!(parse_clock("sundial").unwrap_err().starts_with("Invalid clock: sundial"));
+    }
+
+    #[test]
+    fn fixed_and_simulated_readings() {
+        let mut fixed = Clock::Fixed(T);
+        assert_eq!((fixed.now(), fixed.now()), (Ok(T), Ok(T)));
+
+        let mut simulated = Clock::Simulated { next: T, step: 10 };
+        assert_eq!((simulated.now(), simulated.now(), simulated.now()), (Ok(T), Ok(T + 10), Ok(T + 20)));
+
+        let mut overflow = Clock::Simulated { next: u64::MAX, step: 1 };
+        assert_eq!(overflow.now(), Err("Simulated clock overflow".to_string()));
+    }
+
+    #[test]
+    fn offset_shifts_system_time() {
+        let before = get_nanos_since_epoch().unwrap();
+        let shifted = Clock::Offset(-1_000_000_000_000).now().unwrap();
+        assert!(shifted < before);
+        // 当前时间减去 i64::MIN 必然低于 0
+        assert_eq!(Clock::Offset(i64::MIN).now(), Err(format!("Clock offset {} out of range", i64::MIN)));
+    }
+
+    #[test]
+    fn display_round_trips() {
+        for clock in [Clock::System, Clock::Fixed(T), Clock::Offset(-5), Clock::Simulated { next: T, step: 7 }] {
+            assert_eq!(parse_clock(&clock.to_string()), Ok(clock));
+        }
+    }
+}
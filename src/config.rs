@@ -0,0 +1,129 @@
+// src/config.rs
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use clap::parser::ValueSource;
+use clap::{ArgMatches, CommandFactory, FromArgMatches};
+use serde::Deserialize;
+
+use crate::params::{Args, OutputFormat};
+use crate::risk::RiskConfig;
+
+// --- 配置文件 (TOML) ---
+//
+// default_profile = "dev"
+//
+// [profiles.dev]
+// trade_addr = "239.0.0.1:5000"
+// result_addr = ["239.0.0.2:5001"]
+// interface = "lo"
+// ttl = 1
+// output_format = "json"
+// default_product = 7
+// products_file = "/etc/trading-client/products.toml"
+// session_close = "16:00"
+//
+// [profiles.prod.risk]
+// max_orders_per_second = 10
+//
+// 优先级：命令行参数 > 环境变量 > profile > 内置默认值
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ConfigFile {
+    pub default_profile: Option<String>,
+    #[serde(default)]
+    pub profiles: BTreeMap<String, Profile>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Profile {
+    pub trade_addr: Option<String>,
+    pub result_addr: Option<Vec<String>>,
+    pub interface: Option<String>,
+    pub ttl: Option<u32>,
+    pub output_format: Option<OutputFormat>,
+    pub default_product: Option<u16>,
+    pub products_file: Option<PathBuf>, // 未指定 --products 时使用
+    pub session_close: Option<String>,  // DAY 订单的收盘时间 HH:MM[:SS]
+    pub risk: Option<RiskConfig>, // 未指定 --risk-config 时使用
+}
+
+// 默认配置文件：~/.config/trading-client/config.toml
+pub fn default_config_path() -> Option<PathBuf> {
+    dirs::config_dir().map(|dir| dir.join("trading-client").join("config.toml"))
+}
+
+impl ConfigFile {
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let text = fs::read_to_string(path)
+            .map_err(|e| format!("Failed to read config {}: {}", path.display(), e))?;
+        toml::from_str(&text)
+            .map_err(|e| format!("Invalid config {}: {}", path.display(), e))
+    }
+}
+
+// 选出要使用的 profile：--profile 优先，其次为配置文件中的 default_profile
+fn select_profile(args: &Args) -> Result<Option<(String, Profile)>, String> {
+    let path = match args.config.clone().or_else(default_config_path) {
+        Some(path) => path,
+        None => return Ok(None),
+    };
+    // 只有默认路径允许不存在
+    if args.config.is_none() && !path.exists() {
+        return match &args.profile {
+            Some(name) => Err(format!("Profile '{}' requested but config file {} does not exist", name, path.display())),
+            None => Ok(None),
+        };
+    }
+
+    let mut config = ConfigFile::load(&path)?;
+    let name = match args.profile.clone().or(config.default_profile.clone()) {
+        Some(name) => name,
+        None => return Ok(None),
+    };
+    let profile = config.profiles.remove(&name).ok_or_else(|| {
+        let known: Vec<&String> = config.profiles.keys().collect();
+        format!("Profile '{}' not found in {} (available: {:?})", name, path.display(), known)
+    })?;
+    Ok(Some((name, profile)))
+}
+
+// 参数值是否来自内置默认值 (未在命令行或环境变量中给出)
+fn is_default(matches: &ArgMatches, id: &str) -> bool {
+    !matches!(matches.value_source(id), Some(ValueSource::CommandLine) | Some(ValueSource::EnvVariable))
+}
+
+// 解析命令行，并用 profile 填充未显式指定的参数
+pub fn parse_args() -> Result<(Args, Option<(String, Profile)>), String> {
+    let matches = Args::command().get_matches();
+    let mut args = Args::from_arg_matches(&matches).map_err(|e| e.to_string())?;
+
+    let selected = select_profile(&args)?;
+    if let Some((_, profile)) = &selected {
+        if let Some(trade_addr) = &profile.trade_addr
+            && is_default(&matches, "trade_addr") {
+            args.trade_addr = trade_addr.clone();
+        }
+        if let Some(result_addr) = &profile.result_addr
+            && is_default(&matches, "result_addr") && args.line_a.is_none() {
+            args.result_addr = result_addr.clone();
+        }
+        if let Some(interface) = &profile.interface
+            && is_default(&matches, "interface") {
+            args.interface = interface.clone();
+        }
+        if let Some(ttl) = profile.ttl
+            && is_default(&matches, "ttl") {
+            args.ttl = ttl;
+        }
+        if let Some(output_format) = profile.output_format
+            && is_default(&matches, "output_format") {
+            args.output_format = output_format;
+        }
+    }
+    Ok((args, selected))
+}
@@ -1,15 +1,19 @@
 // src/encoding.rs
 
 use crate::types::{Order, MESSAGE_TOTAL_SIZE, MSG_ORDER_SUBMIT,MSG_TRADE_BROADCAST,MSG_STATUS_BROADCAST};
-use crate::types::{MatchResult, BroadcastStats};
+use crate::types::{RetransmitRequest, MSG_RETRANSMIT_REQUEST, MSG_ORDER_CANCEL};
+use crate::types::{MatchResult, BroadcastStats, SEQUENCE_OFFSET, SEQUENCE_NONE, format_instance_tag};
+use crate::types::{message_type_name, ORDER_EXTENSION_OFFSET};
+use crate::types::{ORDER_PRODUCT_ID_OFFSET, ORDER_ID_OFFSET, ORDER_PRICE_OFFSET, ORDER_QUANTITY_OFFSET, ORDER_SIDE_OFFSET};
+use crate::types::{ORDER_PRICE_TYPE_OFFSET, ORDER_SUBMIT_TIME_OFFSET, ORDER_EXPIRE_TIME_OFFSET, CANCEL_ORDER_ID_OFFSET};
+use crate::types::{RETRANSMIT_INSTANCE_TAG_OFFSET, RETRANSMIT_START_SEQUENCE_OFFSET, RETRANSMIT_END_SEQUENCE_OFFSET};
+use crate::types::{TRADE_INSTANCE_TAG_OFFSET, TRADE_PRODUCT_ID_OFFSET, TRADE_BUY_ORDER_ID_OFFSET, TRADE_SELL_ORDER_ID_OFFSET};
+use crate::types::{TRADE_PRICE_OFFSET, TRADE_QUANTITY_OFFSET, TRADE_NETWORK_TIME_OFFSET, TRADE_INTERNAL_MATCH_TIME_OFFSET};
+use crate::types::{STATS_INSTANCE_TAG_OFFSET, STATS_PRODUCT_ID_OFFSET, STATS_BIDS_SIZE_OFFSET, STATS_ASK_SIZE_OFFSET};
+use crate::types::{STATS_MATCHED_ORDERS_OFFSET, STATS_TOTAL_RECEIVED_ORDERS_OFFSET, STATS_START_TIME_OFFSET};
 
 use std::convert::TryInto; // 用于 slice 转固定大小数组
-
-// Payload starts after Checksum (1 byte) and Message Type (1 byte)
-const PAYLOAD_START: usize = 2;
-
-
-
+use std::fmt;
 
 // 假设的 Checksum 计算函数
 pub fn calculate_checksum(buf: &[u8]) -> u8 {
@@ -20,27 +24,24 @@ pub fn calculate_checksum(buf: &[u8]) -> u8 {
 // 序列化 Order 结构体
 pub fn serialize_order(order: &Order) -> [u8; MESSAGE_TOTAL_SIZE] {
     let mut buf = [0u8; MESSAGE_TOTAL_SIZE];
-    let payload_start = 2; // Checksum (0) + Type (1) = Start at index 2
-
     buf[1] = MSG_ORDER_SUBMIT;
 
-    // 结构体字段序列化... (大端序)
-    // Product ID (u16)
-    buf[payload_start..payload_start + 2].copy_from_slice(&order.product_id.to_be_bytes());
-    // Order ID (u64)
-    buf[payload_start + 2..payload_start + 10].copy_from_slice(&order.order_id.to_be_bytes());
-    // Price (u64)
-    buf[payload_start + 10..payload_start + 18].copy_from_slice(&order.price.to_be_bytes());
-    // Quantity (u32)
-    buf[payload_start + 18..payload_start + 22].copy_from_slice(&order.quantity.to_be_bytes());
-    // Order Type (u8)
-    buf[payload_start + 22] = order.order_type;
-    // Price Type (u8)
-    buf[payload_start + 23] = order.price_type;
-    // Submit Time (u64)
-    buf[payload_start + 24..payload_start + 32].copy_from_slice(&order.submit_time.to_be_bytes());
-    // Expire Time (u64)
-    buf[payload_start + 32..payload_start + 40].copy_from_slice(&order.expire_time.to_be_bytes());
+    // 结构体字段序列化 (大端序)，偏移见 types.rs 的 ORDER_*_OFFSET
+    buf[ORDER_PRODUCT_ID_OFFSET..ORDER_PRODUCT_ID_OFFSET + 2].copy_from_slice(&order.product_id.to_be_bytes());
+    buf[ORDER_ID_OFFSET..ORDER_ID_OFFSET + 8].copy_from_slice(&order.order_id.to_be_bytes());
+    buf[ORDER_PRICE_OFFSET..ORDER_PRICE_OFFSET + 8].copy_from_slice(&order.price.to_be_bytes());
+    buf[ORDER_QUANTITY_OFFSET..ORDER_QUANTITY_OFFSET + 4].copy_from_slice(&order.quantity.to_be_bytes());
+    buf[ORDER_SIDE_OFFSET] = order.order_type;
+    buf[ORDER_PRICE_TYPE_OFFSET] = order.price_type;
+    buf[ORDER_SUBMIT_TIME_OFFSET..ORDER_SUBMIT_TIME_OFFSET + 8].copy_from_slice(&order.submit_time.to_be_bytes());
+    buf[ORDER_EXPIRE_TIME_OFFSET..ORDER_EXPIRE_TIME_OFFSET + 8].copy_from_slice(&order.expire_time.to_be_bytes());
+
+    // 扩展字段 (空闲字节 42..50)：止损触发价或冰山显示数量
+    if order.is_stop() {
+        buf[ORDER_EXTENSION_OFFSET..ORDER_EXTENSION_OFFSET + 8].copy_from_slice(&order.trigger_price.to_be_bytes());
+    } else if order.is_iceberg() {
+        buf[ORDER_EXTENSION_OFFSET..ORDER_EXTENSION_OFFSET + 4].copy_from_slice(&order.display_quantity.to_be_bytes());
+    }
 
     // Checksum calculation and placement
     buf[0] = calculate_checksum(&buf);
@@ -49,6 +50,91 @@ pub fn serialize_order(order: &Order) -> [u8; MESSAGE_TOTAL_SIZE] {
 }
 
 
+// 反序列化 Order (订单日志回放用)，字段布局与 serialize_order 一致
+pub fn deserialize_order(buf: &[u8]) -> Result<Order, String> {
+    if buf.len() < MESSAGE_TOTAL_SIZE || buf[1] != MSG_ORDER_SUBMIT {
+        return Err("Buffer is not a MSG_ORDER_SUBMIT frame".to_string());
+    }
+    let u64_at = |i: usize| u64::from_be_bytes(buf[i..i + 8].try_into().unwrap());
+    let u32_at = |i: usize| u32::from_be_bytes(buf[i..i + 4].try_into().unwrap());
+
+    let mut order = Order {
+        product_id: u16::from_be_bytes([buf[ORDER_PRODUCT_ID_OFFSET], buf[ORDER_PRODUCT_ID_OFFSET + 1]]),
+        order_id: u64_at(ORDER_ID_OFFSET),
+        price: u64_at(ORDER_PRICE_OFFSET),
+        quantity: u32_at(ORDER_QUANTITY_OFFSET),
+        order_type: buf[ORDER_SIDE_OFFSET],
+        price_type: buf[ORDER_PRICE_TYPE_OFFSET],
+        submit_time: u64_at(ORDER_SUBMIT_TIME_OFFSET),
+        expire_time: u64_at(ORDER_EXPIRE_TIME_OFFSET),
+        trigger_price: 0,
+        display_quantity: 0,
+    };
+    if order.is_stop() {
+        order.trigger_price = u64_at(ORDER_EXTENSION_OFFSET);
+    } else if order.is_iceberg() {
+        order.display_quantity = u32_at(ORDER_EXTENSION_OFFSET);
+    }
+    Ok(order)
+}
+
+// 序列化撤单消息
+pub fn serialize_cancel(order_id: u64) -> [u8; MESSAGE_TOTAL_SIZE] {
+    let mut buf = [0u8; MESSAGE_TOTAL_SIZE];
+    buf[1] = MSG_ORDER_CANCEL; // 消息类型
+
+    // Order ID
+    buf[CANCEL_ORDER_ID_OFFSET..CANCEL_ORDER_ID_OFFSET + 8].copy_from_slice(&order_id.to_be_bytes());
+
+    // 计算 Checksum 并放置
+    buf[0] = calculate_checksum(&buf);
+
+    buf
+}
+
+// 从撤单消息中取出 Order ID
+pub fn deserialize_cancel(buf: &[u8]) -> Result<u64, String> {
+    if buf.len() < MESSAGE_TOTAL_SIZE || buf[1] != MSG_ORDER_CANCEL {
+        return Err("Buffer is not a MSG_ORDER_CANCEL frame".to_string());
+    }
+    Ok(u64::from_be_bytes(buf[CANCEL_ORDER_ID_OFFSET..CANCEL_ORDER_ID_OFFSET + 8].try_into().unwrap()))
+}
+
+// 字节 <-> 十六进制字符串 (订单日志、原始帧工具使用)
+pub fn encode_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+pub fn decode_hex(text: &str) -> Result<Vec<u8>, String> {
+    let digits: Vec<u8> = text.bytes().filter(|b| !b.is_ascii_whitespace()).collect();
+    if !digits.len().is_multiple_of(2) {
+        return Err(format!("Hex string has an odd number of digits ({})", digits.len()));
+    }
+    digits.chunks(2)
+        .map(|pair| {
+            let pair = std::str::from_utf8(pair).map_err(|_| "Invalid hex string".to_string())?;
+            u8::from_str_radix(pair, 16).map_err(|_| format!("Invalid hex digits: {}", pair))
+        })
+        .collect()
+}
+
+// 序列化重传请求
+pub fn serialize_retransmit_request(request: &RetransmitRequest) -> [u8; MESSAGE_TOTAL_SIZE] {
+    let mut buf = [0u8; MESSAGE_TOTAL_SIZE];
+
+    buf[1] = MSG_RETRANSMIT_REQUEST;
+
+    // Instance Tag ([u8; 8])
+    buf[RETRANSMIT_INSTANCE_TAG_OFFSET..RETRANSMIT_INSTANCE_TAG_OFFSET + 8].copy_from_slice(&request.instance_tag);
+    // Start Sequence (u16)
+    buf[RETRANSMIT_START_SEQUENCE_OFFSET..RETRANSMIT_START_SEQUENCE_OFFSET + 2].copy_from_slice(&request.start_sequence.to_be_bytes());
+    // End Sequence (u16)
+    buf[RETRANSMIT_END_SEQUENCE_OFFSET..RETRANSMIT_END_SEQUENCE_OFFSET + 2].copy_from_slice(&request.end_sequence.to_be_bytes());
+
+    buf[0] = calculate_checksum(&buf);
+
+    buf
+}
 
 ///
 /// 解码 MatchResult 结构体
@@ -60,49 +146,42 @@ pub fn deserialize_match_result(buf: &[u8]) -> Result<MatchResult, &'static str>
         return Err("Buffer size is too small for MatchResult.");
     }
 
-    let mut current_idx = PAYLOAD_START;
-
     // 1. Instance Tag ([u8; 8])
-    let instance_tag: [u8; 8] = buf[current_idx..current_idx + 8].try_into().map_err(|_| "Failed to read instance_tag")?;
-    current_idx += 8;
+    let instance_tag: [u8; 8] = buf[TRADE_INSTANCE_TAG_OFFSET..TRADE_INSTANCE_TAG_OFFSET + 8].try_into().map_err(|_| "Failed to read instance_tag")?;
 
     // 2. Product ID (u16)
-    let product_id_bytes: [u8; 2] = buf[current_idx..current_idx + 2].try_into().map_err(|_| "Failed to read product_id")?;
+    let product_id_bytes: [u8; 2] = buf[TRADE_PRODUCT_ID_OFFSET..TRADE_PRODUCT_ID_OFFSET + 2].try_into().map_err(|_| "Failed to read product_id")?;
     let product_id = u16::from_be_bytes(product_id_bytes);
-    current_idx += 2;
 
     // 3. Buy Order ID (u64)
-    let buy_order_id_bytes: [u8; 8] = buf[current_idx..current_idx + 8].try_into().map_err(|_| "Failed to read buy_order_id")?;
+    let buy_order_id_bytes: [u8; 8] = buf[TRADE_BUY_ORDER_ID_OFFSET..TRADE_BUY_ORDER_ID_OFFSET + 8].try_into().map_err(|_| "Failed to read buy_order_id")?;
     let buy_order_id = u64::from_be_bytes(buy_order_id_bytes);
-    current_idx += 8;
 
     // 4. Sell Order ID (u64)
-    let sell_order_id_bytes: [u8; 8] = buf[current_idx..current_idx + 8].try_into().map_err(|_| "Failed to read sell_order_id")?;
+    let sell_order_id_bytes: [u8; 8] = buf[TRADE_SELL_ORDER_ID_OFFSET..TRADE_SELL_ORDER_ID_OFFSET + 8].try_into().map_err(|_| "Failed to read sell_order_id")?;
     let sell_order_id = u64::from_be_bytes(sell_order_id_bytes);
-    current_idx += 8;
 
     // 5. Price (u64)
-    let price_bytes: [u8; 8] = buf[current_idx..current_idx + 8].try_into().map_err(|_| "Failed to read price")?;
+    let price_bytes: [u8; 8] = buf[TRADE_PRICE_OFFSET..TRADE_PRICE_OFFSET + 8].try_into().map_err(|_| "Failed to read price")?;
     let price = u64::from_be_bytes(price_bytes);
-    current_idx += 8;
 
     // 6. Quantity (u32)
-    let quantity_bytes: [u8; 4] = buf[current_idx..current_idx + 4].try_into().map_err(|_| "Failed to read quantity")?;
+    let quantity_bytes: [u8; 4] = buf[TRADE_QUANTITY_OFFSET..TRADE_QUANTITY_OFFSET + 4].try_into().map_err(|_| "Failed to read quantity")?;
     let quantity = u32::from_be_bytes(quantity_bytes);
-    current_idx += 4;
 
     // 7. Trade Time Network (u32)
-    let trade_network_time_bytes: [u8; 4] = buf[current_idx..current_idx + 4].try_into().map_err(|_| "Failed to read trade_network_time")?;
+    let trade_network_time_bytes: [u8; 4] = buf[TRADE_NETWORK_TIME_OFFSET..TRADE_NETWORK_TIME_OFFSET + 4].try_into().map_err(|_| "Failed to read trade_network_time")?;
     let trade_network_time = u32::from_be_bytes(trade_network_time_bytes);
-    current_idx += 4;
 
     // 8. Internal Match Time (u32)
     // 注意: 您的序列化代码中这里实际上是重复写入了 trade_network_time 的值，
     // 解码时，我们根据 MatchResult 结构体字段来读，它应是 internal_match_time
     // 假设序列化代码的意图是 Trade Time (u32) + Internal Match Time (u32)。
-    let internal_match_time_bytes: [u8; 4] = buf[current_idx..current_idx + 4].try_into().map_err(|_| "Failed to read internal_match_time")?;
+    let internal_match_time_bytes: [u8; 4] = buf[TRADE_INTERNAL_MATCH_TIME_OFFSET..TRADE_INTERNAL_MATCH_TIME_OFFSET + 4].try_into().map_err(|_| "Failed to read internal_match_time")?;
     let internal_match_time = u32::from_be_bytes(internal_match_time_bytes);
-    // current_idx += 4; // 不需要再增加，因为这是最后一个字段
+
+    // 9. Sequence Number (u16，可选扩展，位于尾部空闲字节)
+    let sequence = read_sequence(buf)?;
 
     Ok(MatchResult {
         instance_tag,
@@ -113,50 +192,51 @@ pub fn deserialize_match_result(buf: &[u8]) -> Result<MatchResult, &'static str>
         quantity,
         trade_network_time,
         internal_match_time,
+        sequence,
     })
 }
 
 
+// 读取尾部的序列号扩展 (0 表示引擎未填写)
+fn read_sequence(buf: &[u8]) -> Result<u16, &'static str> {
+    let sequence_bytes: [u8; 2] = buf[SEQUENCE_OFFSET..SEQUENCE_OFFSET + 2].try_into().map_err(|_| "Failed to read sequence")?;
+    Ok(u16::from_be_bytes(sequence_bytes))
+}
+
 pub fn deserialize_stats_result(buf: &[u8]) -> Result<BroadcastStats, &'static str> {
     if buf.len() < MESSAGE_TOTAL_SIZE {
         return Err("Buffer size is too small for BroadcastStats.");
     }
     
-    let mut current_idx = PAYLOAD_START;
-
     // 1. Instance Tag ([u8; 8])
-    let instance_tag: [u8; 8] = buf[current_idx..current_idx + 8].try_into().map_err(|_| "Failed to read instance_tag")?;
-    current_idx += 8;
+    let instance_tag: [u8; 8] = buf[STATS_INSTANCE_TAG_OFFSET..STATS_INSTANCE_TAG_OFFSET + 8].try_into().map_err(|_| "Failed to read instance_tag")?;
 
     // 2. Product ID (u16)
-    let product_id_bytes: [u8; 2] = buf[current_idx..current_idx + 2].try_into().map_err(|_| "Failed to read product_id")?;
+    let product_id_bytes: [u8; 2] = buf[STATS_PRODUCT_ID_OFFSET..STATS_PRODUCT_ID_OFFSET + 2].try_into().map_err(|_| "Failed to read product_id")?;
     let product_id = u16::from_be_bytes(product_id_bytes);
-    current_idx += 2;
 
     // 3. Bids Size (u32)
-    let bids_size_bytes: [u8; 4] = buf[current_idx..current_idx + 4].try_into().map_err(|_| "Failed to read bids_size")?;
+    let bids_size_bytes: [u8; 4] = buf[STATS_BIDS_SIZE_OFFSET..STATS_BIDS_SIZE_OFFSET + 4].try_into().map_err(|_| "Failed to read bids_size")?;
     let bids_size = u32::from_be_bytes(bids_size_bytes);
-    current_idx += 4;
 
     // 4. Ask Size (u32)
-    let ask_size_bytes: [u8; 4] = buf[current_idx..current_idx + 4].try_into().map_err(|_| "Failed to read ask_size")?;
+    let ask_size_bytes: [u8; 4] = buf[STATS_ASK_SIZE_OFFSET..STATS_ASK_SIZE_OFFSET + 4].try_into().map_err(|_| "Failed to read ask_size")?;
     let ask_size = u32::from_be_bytes(ask_size_bytes);
-    current_idx += 4;
 
     // 5. Matched Orders (u32)
-    let matched_orders_bytes: [u8; 4] = buf[current_idx..current_idx + 4].try_into().map_err(|_| "Failed to read matched_orders")?;
+    let matched_orders_bytes: [u8; 4] = buf[STATS_MATCHED_ORDERS_OFFSET..STATS_MATCHED_ORDERS_OFFSET + 4].try_into().map_err(|_| "Failed to read matched_orders")?;
     let matched_orders = u32::from_be_bytes(matched_orders_bytes);
-    current_idx += 4;
 
     // 6. Total Received Orders (u32)
-    let total_received_orders_bytes: [u8; 4] = buf[current_idx..current_idx + 4].try_into().map_err(|_| "Failed to read total_received_orders")?;
+    let total_received_orders_bytes: [u8; 4] = buf[STATS_TOTAL_RECEIVED_ORDERS_OFFSET..STATS_TOTAL_RECEIVED_ORDERS_OFFSET + 4].try_into().map_err(|_| "Failed to read total_received_orders")?;
     let total_received_orders = u32::from_be_bytes(total_received_orders_bytes);
-    current_idx += 4;
 
     // 7. Start Time (u64)
-    let start_time_bytes: [u8; 8] = buf[current_idx..current_idx + 8].try_into().map_err(|_| "Failed to read start_time")?;
+    let start_time_bytes: [u8; 8] = buf[STATS_START_TIME_OFFSET..STATS_START_TIME_OFFSET + 8].try_into().map_err(|_| "Failed to read start_time")?;
     let start_time = u64::from_be_bytes(start_time_bytes);
-    // current_idx += 8; // 不需要再增加，因为这是最后一个字段
+
+    // 8. Sequence Number (u16，可选扩展，位于尾部空闲字节)
+    let sequence = read_sequence(buf)?;
 
     Ok(BroadcastStats {
         instance_tag,
@@ -166,12 +246,84 @@ pub fn deserialize_stats_result(buf: &[u8]) -> Result<BroadcastStats, &'static s
         matched_orders,
         total_received_orders,
         start_time,
+        sequence,
     })
 }
 
 
+/// 解码后的广播消息
+#[derive(Debug, Clone)]
+pub enum BroadcastMessage {
+    Trade(MatchResult),
+    Status(BroadcastStats),
+}
+
+impl BroadcastMessage {
+    pub fn instance_tag(&self) -> &[u8; 8] {
+        match self {
+            BroadcastMessage::Trade(result) => &result.instance_tag,
+            BroadcastMessage::Status(stats) => &stats.instance_tag,
+        }
+    }
+
+    pub fn sequence(&self) -> u16 {
+        match self {
+            BroadcastMessage::Trade(result) => result.sequence,
+            BroadcastMessage::Status(stats) => stats.sequence,
+        }
+    }
+
+    // --output-format json 使用的字段表示
+    pub fn to_json(&self) -> serde_json::Value {
+        let mut value = match self {
+            BroadcastMessage::Trade(result) => serde_json::json!({
+                "type": "trade",
+                "product_id": result.product_id,
+                "price": result.price,
+                "quantity": result.quantity,
+                "buy_order_id": result.buy_order_id,
+                "sell_order_id": result.sell_order_id,
+                "trade_network_time": result.trade_network_time,
+                "internal_match_time": result.internal_match_time,
+            }),
+            BroadcastMessage::Status(stats) => serde_json::json!({
+                "type": "status",
+                "product_id": stats.product_id,
+                "bids_size": stats.bids_size,
+                "ask_size": stats.ask_size,
+                "matched_orders": stats.matched_orders,
+                "total_received_orders": stats.total_received_orders,
+                "start_time": stats.start_time,
+            }),
+        };
+        value["instance_tag"] = format_instance_tag(self.instance_tag()).into();
+        if self.sequence() != SEQUENCE_NONE {
+            value["sequence"] = self.sequence().into();
+        }
+        value
+    }
+}
+
+impl fmt::Display for BroadcastMessage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BroadcastMessage::Trade(result) => write!(f, "🔥 TRADE: Product={} | Price={} | Qty={} | BuyID={} | SellId={}| Net={}ns | Match={}ns", 
+                result.product_id, result.price, result.quantity, result.buy_order_id, result.sell_order_id,
+                result.trade_network_time,
+                result.internal_match_time)?,
+            BroadcastMessage::Status(stats) => write!(f, "📊 STATUS: Product={} | BidSize={} | AskSize={} | Matched={} | Received={}", 
+                stats.product_id, stats.bids_size, stats.ask_size, stats.matched_orders, stats.total_received_orders)?,
+        }
+        write!(f, " | Inst={}", format_instance_tag(self.instance_tag()))?;
+        if self.sequence() != SEQUENCE_NONE {
+            write!(f, " | Seq={}", self.sequence())?;
+        }
+        Ok(())
+    }
+}
+
 /// 根据消息类型分派并解码结果
-pub fn decode_broadcast_message(buf: &[u8]) -> Result<String, String> {
+pub fn decode_broadcast_message(buf: &[u8]) -> Result<BroadcastMessage, String> {
     if buf.len() < MESSAGE_TOTAL_SIZE {
         return Err("Received buffer is too small.".to_string());
     }
@@ -185,18 +337,128 @@ pub fn decode_broadcast_message(buf: &[u8]) -> Result<String, String> {
             let result = deserialize_match_result(buf)
                 .map_err(|e| format!("Failed to decode MatchResult: {}", e))?;
             
-            Ok(format!("🔥 TRADE: Product={} | Price={} | Qty={} | BuyID={} | SellId={}| Net={}ns | Match={}ns", 
-                result.product_id, result.price, result.quantity, result.buy_order_id, result.sell_order_id,
-                result.trade_network_time,
-                result.internal_match_time))
+            Ok(BroadcastMessage::Trade(result))
         },
         MSG_STATUS_BROADCAST => {
             let stats = deserialize_stats_result(buf)
                 .map_err(|e| format!("Failed to decode BroadcastStats: {}", e))?;
 
-            Ok(format!("📊 STATUS: Product={} | BidSize={} | AskSize={} | Matched={} | Received={}", 
-                stats.product_id, stats.bids_size, stats.ask_size, stats.matched_orders, stats.total_received_orders))
+            Ok(BroadcastMessage::Status(stats))
         },
-        _ => Err(format!("Unknown or unhandled message type: {:?}", buf)),
+        _ => Err(format!("Unknown or unhandled message type: {} ({})", msg_type, message_type_name(msg_type))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{ORDER_FLAG_ICEBERG, ORDER_FLAG_POST_ONLY, ORDER_PRICE_TYPE_LIMIT, ORDER_PRICE_TYPE_STOP};
+    use crate::types::{ORDER_PRICE_TYPE_STOP_LIMIT, ORDER_TIF_IOC, ORDER_TYPE_BUY, ORDER_TYPE_SELL};
+
+    fn order(price_type: u8) -> Order {
+        Order {
+            product_id: 0x0102,
+            order_id: 0x1122_3344_5566_7788,
+            price: 12_345,
+            quantity: 500,
+            order_type: ORDER_TYPE_SELL,
+            price_type,
+            submit_time: 1_700_000_000_000_000_000,
+            expire_time: 1_700_000_060_000_000_000,
+            trigger_price: 0,
+            display_quantity: 0,
+        }
+    }
+
+    fn round_trip(order: &Order) -> ([u8; MESSAGE_TOTAL_SIZE], Order) {
+        let frame = serialize_order(order);
+        assert_eq!(frame[0], calculate_checksum(&frame));
+        (frame, deserialize_order(&frame).unwrap())
+    }
+
+    fn assert_same(decoded: &Order, order: &Order) {
+        assert_eq!(
+            (decoded.product_id, decoded.order_id, decoded.price, decoded.quantity, decoded.order_type),
+            (order.product_id, order.order_id, order.price, order.quantity, order.order_type));
+        assert_eq!(
+            (decoded.price_type, decoded.submit_time, decoded.expire_time, decoded.trigger_price, decoded.display_quantity),
+            (order.price_type, order.submit_time, order.expire_time, order.trigger_price, order.display_quantity));
+    }
+
+    #[test]
+    fn stop_orders_carry_trigger_price() {
+        for price_type in [ORDER_PRICE_TYPE_STOP, ORDER_PRICE_TYPE_STOP_LIMIT | ORDER_TIF_IOC] {
+            let stop = Order { trigger_price: 0x0A0B_0C0D_0E0F_1011, ..order(price_type) };
+            let (frame, decoded) = round_trip(&stop);
+            assert_eq!(frame[ORDER_EXTENSION_OFFSET..], [0x0A, 0x0B, 0x0C, 0x0D, 0x0E, 0x0F, 0x10, 0x11]);
+            assert_same(&decoded, &stop);
+        }
+    }
+
+    #[test]
+    fn iceberg_orders_carry_display_quantity() {
+        let iceberg = Order { display_quantity: 0x0102_0304, ..order(ORDER_PRICE_TYPE_LIMIT | ORDER_FLAG_ICEBERG | ORDER_FLAG_POST_ONLY) };
+        let (frame, decoded) = round_trip(&iceberg);
+        assert_eq!(frame[ORDER_EXTENSION_OFFSET..], [0x01, 0x02, 0x03, 0x04, 0, 0, 0, 0]);
+        assert_same(&decoded, &iceberg);
+    }
+
+    #[test]
+    fn plain_orders_leave_extension_zero() {
+        // 不适用的扩展字段不写入，也不会被解码出来
+        let plain = Order { trigger_price: 99, display_quantity: 7, order_type: ORDER_TYPE_BUY, ..order(ORDER_PRICE_TYPE_LIMIT) };
+        let (frame, decoded) = round_trip(&plain);
+        assert_eq!(frame[ORDER_EXTENSION_OFFSET..], [0; 8]);
+        assert_eq!((decoded.trigger_price, decoded.display_quantity), (0, 0));
+        assert_eq!(frame[..ORDER_EXTENSION_OFFSET], serialize_order(&Order { trigger_price: 0, display_quantity: 0, ..plain })[..ORDER_EXTENSION_OFFSET]);
+    }
+
+    #[test]
+    fn order_fields_are_big_endian() {
+        let (frame, _) = round_trip(&order(ORDER_PRICE_TYPE_LIMIT));
+        assert_eq!(frame[1], MSG_ORDER_SUBMIT);
+        assert_eq!(frame[2..4], [0x01, 0x02]);
+        assert_eq!(frame[4..12], [0x11, 0x22, 0x33, 0x44, 0x55, 0x66, 0x77, 0x88]);
+        assert_eq!(frame[24], ORDER_TYPE_SELL);
+        assert_eq!(frame[25], ORDER_PRICE_TYPE_LIMIT);
+    }
+
+    #[test]
+    fn cancel_round_trip() {
+        let frame = serialize_cancel(42);
+        assert_eq!(frame[0], calculate_checksum(&frame));
+        assert_eq!(deserialize_cancel(&frame), Ok(42));
+        assert!(deserialize_order(&frame).is_err());
+        assert!(deserialize_cancel(&serialize_order(&order(ORDER_PRICE_TYPE_LIMIT))).is_err());
+    }
+
+    #[test]
+    fn stats_round_trip_with_sequence() {
+        let stats = BroadcastStats {
+            instance_tag: *b"ENGINE-A",
+            product_id: 7,
+            bids_size: 1,
+            ask_size: 2,
+            matched_orders: 3,
+            total_received_orders: 4,
+            start_time: 5,
+            sequence: 0,
+        };
+        let mut frame = crate::types::serialize_stats_result(&stats);
+        assert_eq!(frame[0], calculate_checksum(&frame));
+        frame[SEQUENCE_OFFSET..].copy_from_slice(&65535u16.to_be_bytes());
+        let BroadcastMessage::Status(decoded) = decode_broadcast_message(&frame).unwrap() else {
+            panic!("expected a status message");
+        };
+        assert_eq!((decoded.instance_tag, decoded.product_id, decoded.start_time, decoded.sequence), (stats.instance_tag, 7, 5, 65535));
+        assert_eq!((decoded.bids_size, decoded.ask_size, decoded.matched_orders, decoded.total_received_orders), (1, 2, 3, 4));
+    }
+
+    #[test]
+    fn hex_round_trip() {
+        assert_eq!(encode_hex(&[0x00, 0xab, 0x10]), "00ab10");
+        assert_eq!(decode_hex("00 AB\n10"), Ok(vec![0x00, 0xab, 0x10]));
+        assert!(decode_hex("abc").is_err());
+        assert!(decode_hex("zz").is_err());
     }
 }
@@ -0,0 +1,263 @@
+// src/execution.rs
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant};
+
+use crate::encoding::BroadcastMessage;
+use crate::feed::FeedHandler;
+use crate::gateway::OrderGateway;
+use crate::listener::GroupListener;
+use crate::params::Algo;
+use crate::position::Fill;
+use crate::product::ProductMaster;
+use crate::types::{Order, ORDER_PRICE_TYPE_LIMIT, ORDER_PRICE_TYPE_MARKET, ORDER_TYPE_BUY};
+
+// 执行循环的轮询间隔 (也是发出子单的最小时间粒度)
+const EXECUTION_POLL_INTERVAL: Duration = Duration::from_millis(50);
+// 撤掉剩余子单后继续等待在途成交的时间
+const FILL_GRACE_PERIOD: Duration = Duration::from_millis(500);
+
+impl Algo {
+    pub fn name(self) -> &'static str {
+        match self {
+            Algo::Twap => "TWAP",
+            Algo::Vwap => "VWAP",
+        }
+    }
+}
+
+// 母单参数
+#[derive(Debug, Clone)]
+pub struct ExecutionPlan {
+    pub product_id: u16,
+    pub side: u8,
+    pub quantity: u32,
+    pub algo: Algo,
+    pub duration: Duration,
+    pub slices: u32,            // TWAP 切片数
+    pub participation: f64,     // VWAP 参与率 (0..1]
+    pub limit_price: Option<u64>, // 子单限价；None 表示市价子单
+    pub lot_size: u32,
+}
+
+#[derive(Debug)]
+struct Child {
+    order_id: u64,
+    quantity: u32,
+    filled: u32,
+    resting: bool, // 限价子单会挂在订单簿上，结束时需要撤单
+}
+
+// 母单执行状态：子单、成交和行情成交量
+pub struct ParentExecution {
+    plan: ExecutionPlan,
+    started: Instant,
+    children: Vec<Child>,
+    sent: u32,
+    filled: u32,
+    fill_notional: u128,
+    market_volume: u64, // 执行期间的市场成交量 (不含自己的成交)
+    arrival_price: Option<u64>,
+    cancelled: usize,
+}
+
+impl ParentExecution {
+    pub fn new(plan: ExecutionPlan, arrival_price: Option<u64>) -> Self {
+        ParentExecution {
+            plan,
+            started: Instant::now(),
+            children: Vec::new(),
+            sent: 0,
+            filled: 0,
+            fill_notional: 0,
+            market_volume: 0,
+            arrival_price,
+            cancelled: 0,
+        }
+    }
+
+    // 到当前时间为止应发出的累计数量
+    fn target(&self, now: Instant) -> u32 {
+        let total = u64::from(self.plan.quantity);
+        let target = match self.plan.algo {
+            Algo::Twap => {
+                let slices = u64::from(self.plan.slices.max(1));
+                let slice_nanos = (self.plan.duration.as_nanos() / u128::from(slices)).max(1);
+                let elapsed = now.duration_since(self.started).as_nanos();
+                let due_slices = ((elapsed / slice_nanos) as u64 + 1).min(slices);
+                total * due_slices / slices
+            }
+            Algo::Vwap => ((self.market_volume as f64 * self.plan.participation) as u64).min(total),
+        };
+        target as u32
+    }
+
+    // 下一张子单的数量 (按整手取整，最后一张补足余量)
+    fn next_child_quantity(&self, now: Instant) -> Option<u32> {
+        let due = self.target(now).saturating_sub(self.sent);
+        let remaining = self.plan.quantity - self.sent;
+        let quantity = if due >= remaining {
+            remaining
+        } else {
+            due - due % self.plan.lot_size.max(1)
+        };
+        (quantity > 0).then_some(quantity)
+    }
+
+    fn child_order(&self, quantity: u32, now: u64) -> Order {
+        let (price, price_type) = match self.plan.limit_price {
+            Some(price) => (price, ORDER_PRICE_TYPE_LIMIT),
+            None => (0, ORDER_PRICE_TYPE_MARKET),
+        };
+        Order {
+            product_id: self.plan.product_id,
+            order_id: now,
+            price,
+            quantity,
+            order_type: self.plan.side,
+            price_type,
+            submit_time: now,
+            expire_time: 0,
+            trigger_price: 0,
+            display_quantity: 0,
+        }
+    }
+
+    fn is_own(&self, order_id: u64) -> bool {
+        self.children.iter().any(|child| child.order_id == order_id)
+    }
+
+    // 行情：记录市场成交量 (VWAP) 和到达价格 (启动时没有最近成交价则取第一笔成交)
+    pub fn observe(&mut self, message: &BroadcastMessage) {
+        let BroadcastMessage::Trade(result) = message else {
+            return;
+        };
+        if result.product_id != self.plan.product_id {
+            return;
+        }
+        if self.arrival_price.is_none() {
+            self.arrival_price = Some(result.price);
+        }
+        if !self.is_own(result.buy_order_id) && !self.is_own(result.sell_order_id) {
+            self.market_volume += u64::from(result.quantity);
+        }
+    }
+
+    pub fn record_fill(&mut self, fill: &Fill) {
+        if let Some(child) = self.children.iter_mut().find(|child| child.order_id == fill.order_id) {
+            child.filled += fill.quantity;
+            self.filled += fill.quantity;
+            self.fill_notional += u128::from(fill.price) * u128::from(fill.quantity);
+        }
+    }
+
+    fn is_complete(&self) -> bool {
+        self.filled >= self.plan.quantity
+    }
+
+    pub fn print_report(&self, products: &ProductMaster) {
+        let plan = &self.plan;
+        let side = if plan.side == ORDER_TYPE_BUY { "BUY" } else { "SELL" };
+        let price = |price: u64| products.format_price(plan.product_id, price);
+
+        println!("\n=============================================");
+        println!("Execution Report ({})", plan.algo.name());
+        println!("=============================================");
+        println!("Parent: {} {} of product {} over {:.1}s", side, plan.quantity, plan.product_id, plan.duration.as_secs_f64());
+        println!("Children: {} sent (qty {}) | {} cancelled", self.children.len(), self.sent, self.cancelled);
+        println!("Filled: {} / {} ({:.1}%)", self.filled, plan.quantity, f64::from(self.filled) * 100.0 / f64::from(plan.quantity));
+        println!("Market Volume: {} | Participation: {:.1}%", self.market_volume,
+            if self.market_volume > 0 { f64::from(self.filled) * 100.0 / self.market_volume as f64 } else { 0.0 });
+
+        let arrival = match self.arrival_price {
+            Some(arrival) => arrival,
+            None => {
+                println!("Arrival Price: - (no trades seen)");
+                return;
+            }
+        };
+        println!("Arrival Price: {}", price(arrival));
+        if self.filled == 0 {
+            return;
+        }
+
+        // 滑点为正表示比到达价格差 (买得更贵 / 卖得更便宜)
+        let average = self.fill_notional as f64 / f64::from(self.filled);
+        let sign = if plan.side == ORDER_TYPE_BUY { 1.0 } else { -1.0 };
+        let slippage = (average - arrival as f64) * sign;
+        let bps = if arrival > 0 { slippage / arrival as f64 * 10_000.0 } else { 0.0 };
+        let scale = products.by_id(plan.product_id).map(|info| 10f64.powi(info.price_decimals as i32)).unwrap_or(1.0);
+        println!("Avg Fill Price: {:.4}", average / scale);
+        println!("Slippage: {:.4} ({:.1} bps)", slippage / scale, bps);
+    }
+}
+
+// 执行母单：按计划发出子单、跟踪成交，到时撤掉剩余子单
+pub fn run(
+    execution: &mut ParentExecution,
+    listener: &mut GroupListener,
+    feed: &mut FeedHandler,
+    gateway: &mut OrderGateway,
+    running: &AtomicBool,
+) -> Result<(), String> {
+    let deadline = execution.started + execution.plan.duration;
+    let mut delivered = Vec::new();
+    let mut fills = Vec::new();
+
+    let mut process = |execution: &mut ParentExecution, gateway: &mut OrderGateway, listener: &mut GroupListener| -> Result<(), String> {
+        feed.poll(listener, EXECUTION_POLL_INTERVAL, &mut delivered)?;
+        for tagged in delivered.drain(..) {
+            gateway.observe(&tagged.message, &mut fills)?;
+            execution.observe(&tagged.message);
+            for fill in fills.drain(..) {
+                execution.record_fill(&fill);
+                println!("{}", fill);
+            }
+        }
+        Ok(())
+    };
+
+    while running.load(Ordering::SeqCst) && Instant::now() < deadline && !execution.is_complete() {
+        if let Some(quantity) = execution.next_child_quantity(Instant::now()) {
+            // 固定时钟下也保证子单 ID 不重复
+            let last_id = execution.children.last().map(|child| child.order_id);
+            let now = gateway.now()?.max(last_id.map_or(0, |id| id + 1));
+            let order = execution.child_order(quantity, now);
+            match gateway.submit(&order) {
+                Ok(_) => {
+                    execution.sent += quantity;
+                    execution.children.push(Child {
+                        order_id: order.order_id,
+                        quantity,
+                        filled: 0,
+                        resting: order.price_type == ORDER_PRICE_TYPE_LIMIT,
+                    });
+                    println!("➡️  CHILD {}: OrderID={} | Qty={} | Sent={}/{}",
+                        execution.children.len(), order.order_id, quantity, execution.sent, execution.plan.quantity);
+                }
+                // 风控拒绝时停止发出新的子单
+                Err(e) => {
+                    eprintln!("Child order rejected, stopping execution: {}", e);
+                    break;
+                }
+            }
+        }
+        process(execution, gateway, listener)?;
+    }
+
+    // 撤掉未成交完的限价子单，再等待在途成交
+    let open: Vec<u64> = execution.children.iter()
+        .filter(|child| child.resting && child.filled < child.quantity)
+        .map(|child| child.order_id)
+        .collect();
+    for order_id in open {
+        gateway.cancel(order_id)?;
+        execution.cancelled += 1;
+        println!("✖️  CANCEL CHILD: OrderID={}", order_id);
+    }
+    let grace_end = Instant::now() + FILL_GRACE_PERIOD;
+    while running.load(Ordering::SeqCst) && Instant::now() < grace_end && !execution.is_complete() {
+        process(execution, gateway, listener)?;
+    }
+    Ok(())
+}
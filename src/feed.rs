@@ -0,0 +1,167 @@
+// src/feed.rs
+
+use std::io;
+use std::net::IpAddr;
+use std::time::{Duration, Instant};
+
+use crate::arbitration::Arbiter;
+use crate::encoding::{calculate_checksum, decode_broadcast_message, BroadcastMessage};
+use crate::instance::InstanceTracker;
+use crate::listener::{GroupListener, ReceivedFrame, TaggedMessage};
+use crate::recovery::RecoveryBuffer;
+use crate::sequence::{prev_sequence, SequenceEvent, SequenceTracker};
+use crate::summary::SessionSummary;
+use crate::transport::Endpoint;
+use crate::hexdump::{render, use_color};
+use crate::params::FrameDisplay;
+use crate::types::{format_instance_tag, MESSAGE_TOTAL_SIZE};
+
+// 广播处理流水线：来源过滤 → 校验和 → 解码 → A/B 仲裁 → 序列号检查 → 重传恢复 → 实例跟踪，
+// 最终把按序、已过滤的消息交给调用方 (打印、成交跟踪等)
+pub struct FeedHandler {
+    allowed_sources: Vec<IpAddr>,
+    summary: SessionSummary,
+    arbiter: Option<Arbiter>,
+    sequences: SequenceTracker,
+    recovery: Option<RecoveryBuffer>,
+    instances: InstanceTracker,
+    frames: Vec<ReceivedFrame>,         // 本次 poll 收到的原始帧
+    delivered: Vec<TaggedMessage>,
+    frame_display: FrameDisplay,        // 解码失败时是否显示原始帧
+}
+
+impl FeedHandler {
+    pub fn new(allowed_sources: Vec<IpAddr>, arbiter: Option<Arbiter>, recovery: Option<RecoveryBuffer>, instances: InstanceTracker, frame_display: FrameDisplay) -> Self {
+        FeedHandler {
+            allowed_sources,
+            summary: SessionSummary::new(),
+            arbiter,
+            sequences: SequenceTracker::new(),
+            recovery,
+            instances,
+            frames: Vec::new(),
+            delivered: Vec::new(),
+            frame_display,
+        }
+    }
+
+    // 轮询一次监听器，把可交付的消息追加到 out
+    pub fn poll(&mut self, listener: &mut GroupListener, timeout: Duration, out: &mut Vec<TaggedMessage>) -> Result<(), String> {
+        if let Err(e) = listener.poll_frames(Some(timeout), &mut self.frames) {
+            // 忽略非致命错误，例如 EINTR
+            if e.kind() != io::ErrorKind::Interrupted {
+                return Err(format!("Socket receive error: {}", e));
+            }
+        }
+
+        for frame in std::mem::take(&mut self.frames) {
+            self.process_frame(listener, &frame);
+        }
+
+        if let Some(arbiter) = self.arbiter.as_mut() {
+            arbiter.expire(Instant::now());
+        }
+        if let Some(recovery) = self.recovery.as_mut() {
+            recovery.expire(&mut self.delivered);
+        }
+
+        for tagged in self.delivered.drain(..) {
+            if let Some(event) = self.instances.observe(&tagged.message) {
+                eprintln!("[{}] {}", listener.endpoint(tagged.group), event);
+            }
+            if !self.instances.accepts(&tagged.message) {
+                continue;
+            }
+            self.summary.record_message(&tagged.message);
+            out.push(tagged);
+        }
+
+        Ok(())
+    }
+
+    fn process_frame(&mut self, listener: &GroupListener, frame: &ReceivedFrame) {
+        let src = frame.src;
+        let group = listener.endpoint(frame.group);
+
+        // 来源白名单：普通 (任意源) 组播加入时由这里过滤
+        if !self.allowed_sources.is_empty() && !self.allowed_sources.contains(&src.ip()) {
+            eprintln!("[{}] [{}] Dropped frame from source not in allow-list", group, src);
+            self.summary.record_dropped_source();
+            return;
+        }
+
+        let buf = frame.bytes();
+        if buf.len() > 1 {
+            self.summary.record_frame(buf[1]);
+        }
+        // 校验和不一致时只计数，仍然尝试解码
+        let checksum_ok = buf.len() == MESSAGE_TOTAL_SIZE && calculate_checksum(buf) == buf[0]; 
+        if !checksum_ok {
+            self.summary.record_checksum_failure();
+        }
+        let message = match decode_broadcast_message(buf) {
+            Ok(decoded_msg) => decoded_msg,
+            Err(e) => {
+                self.summary.record_decode_error();
+                eprintln!("[{}] [{}] Error decoding message: {}", group, src, e);
+                if self.frame_display != FrameDisplay::None {
+                    eprint!("{}", render(buf, use_color(&std::io::stderr())));
+                }
+                return;
+            }
+        };
+
+        // A/B 线路：另一条线路已交付过的副本直接丢弃
+        if let Some(arbiter) = self.arbiter.as_mut()
+            && !arbiter.accept(frame.group, &message, frame.received_at) {
+            return;
+        }
+
+        let event = report_sequence(&mut self.sequences, &message, group);
+        let tagged = TaggedMessage { group: frame.group, src, message };
+        match self.recovery.as_mut() {
+            Some(recovery) => {
+                // 发现缺口时请求重传，重传帧到达后按序交付
+                if let SequenceEvent::Gap { expected, received, .. } = event
+                    && let Err(e) = recovery.request(tagged.message.instance_tag(), expected, prev_sequence(received)) {
+                    eprintln!("Warning: {}", e);
+                }
+                recovery.accept(tagged, &mut self.delivered);
+            }
+            None => self.delivered.push(tagged),
+        }
+    }
+
+    // 会话结束时的统计报告
+    pub fn print_summary(&self) {
+        self.summary.print();
+        if let Some(arbiter) = &self.arbiter {
+            arbiter.print();
+        }
+        self.sequences.print();
+        if let Some(recovery) = &self.recovery {
+            recovery.print();
+        }
+        self.instances.print();
+    }
+}
+
+// 检查序列号并报告丢包、重复和乱序
+fn report_sequence(sequences: &mut SequenceTracker, message: &BroadcastMessage, group: &Endpoint) -> SequenceEvent {
+    let instance = message.instance_tag();
+    let event = sequences.check(instance, message.sequence());
+    match &event {
+        SequenceEvent::Gap { expected, received, missing } => {
+            eprintln!("[{}] ⚠️  GAP: Instance={} | Expected={} | Received={} | Missing={}",
+                group, format_instance_tag(instance), expected, received, missing);
+        }
+        SequenceEvent::Duplicate { sequence } => {
+            eprintln!("[{}] ⚠️  DUPLICATE: Instance={} | Seq={}", group, format_instance_tag(instance), sequence);
+        }
+        SequenceEvent::Reordered { sequence } => {
+            eprintln!("[{}] ⚠️  REORDERED: Instance={} | Seq={} arrived late", group, format_instance_tag(instance), sequence);
+        }
+        SequenceEvent::NotPresent | SequenceEvent::First | SequenceEvent::InOrder => {}
+    }
+    event
+}
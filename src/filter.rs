@@ -0,0 +1,437 @@
+// src/filter.rs
+
+use std::fmt;
+
+use crate::encoding::BroadcastMessage;
+use crate::types::{message_type_name, MSG_STATUS_BROADCAST, MSG_TRADE_BROADCAST};
+
+// 表达式中可以引用的字段
+const TRADE_FIELDS: &[&str] = &["product_id", "price", "quantity", "buy_order_id", "sell_order_id",
+    "trade_network_time", "internal_match_time", "sequence"];
+const STATUS_FIELDS: &[&str] = &["product_id", "bids_size", "ask_size", "matched_orders",
+    "total_received_orders", "start_time", "sequence"];
+
+// 读取消息的数值字段；该类型消息没有这个字段时返回 None
+fn field_value(message: &BroadcastMessage, field: &str) -> Option<u64> {
+    match message {
+        BroadcastMessage::Trade(r) => match field {
+            "product_id" => Some(u64::from(r.product_id)),
+            "price" => Some(r.price),
+            "quantity" => Some(u64::from(r.quantity)),
+            "buy_order_id" => Some(r.buy_order_id),
+            "sell_order_id" => Some(r.sell_order_id),
+            "trade_network_time" => Some(u64::from(r.trade_network_time)),
+            "internal_match_time" => Some(u64::from(r.internal_match_time)),
+            "sequence" => Some(u64::from(r.sequence)),
+            _ => None,
+        },
+        BroadcastMessage::Status(s) => match field {
+            "product_id" => Some(u64::from(s.product_id)),
+            "bids_size" => Some(u64::from(s.bids_size)),
+            "ask_size" => Some(u64::from(s.ask_size)),
+            "matched_orders" => Some(u64::from(s.matched_orders)),
+            "total_received_orders" => Some(u64::from(s.total_received_orders)),
+            "start_time" => Some(s.start_time),
+            "sequence" => Some(u64::from(s.sequence)),
+            _ => None,
+        },
+    }
+}
+
+// --- 过滤表达式，例如 price > 100 && quantity >= 10 ---
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CompareOp {
+    Eq,
+    Ne,
+    Gt,
+    Ge,
+    Lt,
+    Le,
+}
+
+#[derive(Debug, Clone)]
+pub enum Expr {
+    Compare { field: String, op: CompareOp, value: u64 },
+    And(Box<Expr>, Box<Expr>),
+    Or(Box<Expr>, Box<Expr>),
+    Not(Box<Expr>),
+}
+
+impl Expr {
+    // 字段不属于该消息类型时比较结果为 false
+    pub fn eval(&self, message: &BroadcastMessage) -> bool {
+        match self {
+            Expr::Compare { field, op, value } => match field_value(message, field) {
+                Some(actual) => match op {
+                    CompareOp::Eq => actual == *value,
+                    CompareOp::Ne => actual != *value,
+                    CompareOp::Gt => actual > *value,
+                    CompareOp::Ge => actual >= *value,
+                    CompareOp::Lt => actual < *value,
+                    CompareOp::Le => actual <= *value,
+                },
+                None => false,
+            },
+            Expr::And(left, right) => left.eval(message) && right.eval(message),
+            Expr::Or(left, right) => left.eval(message) || right.eval(message),
+            Expr::Not(inner) => !inner.eval(message),
+        }
+    }
+}
+
+impl fmt::Display for CompareOp {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            CompareOp::Eq => "==",
+            CompareOp::Ne => "!=",
+            CompareOp::Gt => ">",
+            CompareOp::Ge => ">=",
+            CompareOp::Lt => "<",
+            CompareOp::Le => "<=",
+        })
+    }
+}
+
+impl fmt::Display for Expr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Expr::Compare { field, op, value } => write!(f, "{} {} {}", field, op, value),
+            Expr::And(left, right) => write!(f, "({} && {})", left, right),
+            Expr::Or(left, right) => write!(f, "({} || {})", left, right),
+            Expr::Not(inner) => write!(f, "!{}", inner),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Ident(String),
+    Number(u64),
+    Op(CompareOp),
+    And,
+    Or,
+    Not,
+    LParen,
+    RParen,
+}
+
+fn tokenize(input: &str) -> Result<Vec<Token>, String> {
+    let chars: Vec<char> = input.chars().collect();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+
+    while i < chars.len() {
+        let c = chars[i];
+        let next = chars.get(i + 1).copied();
+        match c {
+            ' ' | '\t' => { i += 1; }
+            '(' => { tokens.push(Token::LParen); i += 1; }
+            ')' => { tokens.push(Token::RParen); i += 1; }
+            '&' if next == Some('&') => { tokens.push(Token::And); i += 2; }
+            '|' if next == Some('|') => { tokens.push(Token::Or); i += 2; }
+            '=' if next == Some('=') => { tokens.push(Token::Op(CompareOp::Eq)); i += 2; }
+            '!' if next == Some('=') => { tokens.push(Token::Op(CompareOp::Ne)); i += 2; }
+            '!' => { tokens.push(Token::Not); i += 1; }
+            '>' if next == Some('=') => { tokens.push(Token::Op(CompareOp::Ge)); i += 2; }
+            '>' => { tokens.push(Token::Op(CompareOp::Gt)); i += 1; }
+            '<' if next == Some('=') => { tokens.push(Token::Op(CompareOp::Le)); i += 2; }
+            '<' => { tokens.push(Token::Op(CompareOp::Lt)); i += 1; }
+            c if c.is_ascii_digit() => {
+                let start = i;
+                while i < chars.len() && (chars[i].is_ascii_digit() || chars[i] == '_') {
+                    i += 1;
+                }
+                let text: String = chars[start..i].iter().filter(|&&c| c != '_').collect();
+                let value = text.parse::<u64>()
+                    .map_err(|e| format!("Invalid number '{}': {}", text, e))?;
+                tokens.push(Token::Number(value));
+            }
+            c if c.is_ascii_alphabetic() || c == '_' => {
+                let start = i;
+                while i < chars.len() && (chars[i].is_ascii_alphanumeric() || chars[i] == '_') {
+                    i += 1;
+                }
+                tokens.push(Token::Ident(chars[start..i].iter().collect()));
+            }
+            _ => return Err(format!("Unexpected character '{}' at position {}", c, i)),
+        }
+    }
+
+    Ok(tokens)
+}
+
+// 递归下降解析：or := and ('||' and)* ; and := unary ('&&' unary)* ;
+// unary := '!' unary | '(' or ')' | field op number
+struct ExprParser {
+    tokens: Vec<Token>,
+    pos: usize,
+}
+
+impl ExprParser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.pos).cloned();
+        self.pos += 1;
+        token
+    }
+
+    fn parse_or(&mut self) -> Result<Expr, String> {
+        let mut left = self.parse_and()?;
+        while self.peek() == Some(&Token::Or) {
+            self.pos += 1;
+            left = Expr::Or(Box::new(left), Box::new(self.parse_and()?));
+        }
+        Ok(left)
+    }
+
+    fn parse_and(&mut self) -> Result<Expr, String> {
+        let mut left = self.parse_unary()?;
+        while self.peek() == Some(&Token::And) {
+            self.pos += 1;
+            left = Expr::And(Box::new(left), Box::new(self.parse_unary()?));
+        }
+        Ok(left)
+    }
+
+    fn parse_unary(&mut self) -> Result<Expr, String> {
+        match self.next() {
+            Some(Token::Not) => Ok(Expr::Not(Box::new(self.parse_unary()?))),
+            Some(Token::LParen) => {
+                let inner = self.parse_or()?;
+                match self.next() {
+                    Some(Token::RParen) => Ok(inner),
+                    _ => Err("Expected ')'".to_string()),
+                }
+            }
+            Some(Token::Ident(field)) => {
+                if !TRADE_FIELDS.contains(&field.as_str()) && !STATUS_FIELDS.contains(&field.as_str()) {
+                    return Err(format!("Unknown field '{}'. Trade fields: {}. Status fields: {}",
+                        field, TRADE_FIELDS.join(", "), STATUS_FIELDS.join(", ")));
+                }
+                let op = match self.next() {
+                    Some(Token::Op(op)) => op,
+                    _ => return Err(format!("Expected comparison operator after '{}'", field)),
+                };
+                let value = match self.next() {
+                    Some(Token::Number(value)) => value,
+                    _ => return Err(format!("Expected number after operator for '{}'", field)),
+                };
+                Ok(Expr::Compare { field, op, value })
+            }
+            Some(token) => Err(format!("Unexpected token {:?}", token)),
+            None => Err("Unexpected end of expression".to_string()),
+        }
+    }
+}
+
+pub fn parse_filter_expr(input: &str) -> Result<Expr, String> {
+    let mut parser = ExprParser { tokens: tokenize(input)?, pos: 0 };
+    let expr = parser.parse_or()?;
+    if parser.pos < parser.tokens.len() {
+        return Err(format!("Unexpected trailing input in filter: {:?}", &parser.tokens[parser.pos..]));
+    }
+    Ok(expr)
+}
+
+// --- 价格区间，例如 100..200、100..、..200 (闭区间) ---
+pub fn parse_price_range(s: &str) -> Result<(Option<u64>, Option<u64>), String> {
+    let (low, high) = s.split_once("..")
+        .ok_or_else(|| format!("Invalid price range: {}. Use LOW..HIGH, LOW.. or ..HIGH", s))?;
+    let bound = |text: &str| -> Result<Option<u64>, String> {
+        if text.is_empty() {
+            Ok(None)
+        } else {
+            text.parse::<u64>().map(Some).map_err(|e| format!("Invalid price '{}': {}", text, e))
+        }
+    };
+    Ok((bound(low)?, bound(high)?))
+}
+
+// 监听输出的过滤条件，所有条件同时满足才显示
+#[derive(Debug, Clone, Default)]
+pub struct MessageFilter {
+    pub products: Vec<u16>,
+    pub msg_type: Option<u8>,
+    pub order_id: Option<u64>,
+    pub min_quantity: Option<u32>,
+    pub price_range: Option<(Option<u64>, Option<u64>)>,
+    pub expression: Option<Expr>,
+}
+
+impl MessageFilter {
+    pub fn is_empty(&self) -> bool {
+        self.products.is_empty() && self.msg_type.is_none() && self.order_id.is_none()
+            && self.min_quantity.is_none() && self.price_range.is_none() && self.expression.is_none()
+    }
+
+    pub fn matches(&self, message: &BroadcastMessage) -> bool {
+        let (msg_type, product_id) = match message {
+            BroadcastMessage::Trade(r) => (MSG_TRADE_BROADCAST, r.product_id),
+            BroadcastMessage::Status(s) => (MSG_STATUS_BROADCAST, s.product_id),
+        };
+
+        if !self.products.is_empty() && !self.products.contains(&product_id) {
+            return false;
+        }
+        if self.msg_type.is_some_and(|t| t != msg_type) {
+            return false;
+        }
+
+        // 订单 ID、数量和价格条件只对成交消息有意义，状态消息不满足
+        if self.order_id.is_some() || self.min_quantity.is_some() || self.price_range.is_some() {
+            let BroadcastMessage::Trade(r) = message else {
+                return false;
+            };
+            if self.order_id.is_some_and(|id| r.buy_order_id != id && r.sell_order_id != id) {
+                return false;
+            }
+            if self.min_quantity.is_some_and(|min| r.quantity < min) {
+                return false;
+            }
+            if let Some((low, high)) = self.price_range
+                && (low.is_some_and(|low| r.price < low) || high.is_some_and(|high| r.price > high)) {
+                return false;
+            }
+        }
+
+        self.expression.as_ref().is_none_or(|expr| expr.eval(message))
+    }
+}
+
+impl fmt::Display for MessageFilter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut parts = Vec::new();
+        if !self.products.is_empty() {
+            parts.push(format!("Product in {:?}", self.products));
+        }
+        if let Some(msg_type) = self.msg_type {
+            parts.push(format!("Type={}", message_type_name(msg_type)));
+        }
+        if let Some(order_id) = self.order_id {
+            parts.push(format!("OrderID={}", order_id));
+        }
+        if let Some(min_quantity) = self.min_quantity {
+            parts.push(format!("Qty>={}", min_quantity));
+        }
+        if let Some((low, high)) = self.price_range {
+            let bound = |b: Option<u64>| b.map_or(String::new(), |b| b.to_string());
+            parts.push(format!("Price in {}..{}", bound(low), bound(high)));
+        }
+        if let Some(expr) = &self.expression {
+            parts.push(format!("Expr={}", expr));
+        }
+        write!(f, "{}", parts.join(" | "))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{BroadcastStats, MatchResult};
+
+    fn trade(price: u64, quantity: u32) -> BroadcastMessage {
+        BroadcastMessage::Trade(MatchResult {
+            instance_tag: [0; 8],
+            product_id: 1,
+            buy_order_id: 11,
+            sell_order_id: 22,
+            price,
+            quantity,
+            trade_network_time: 0,
+            internal_match_time: 0,
+            sequence: 0,
+        })
+    }
+
+    fn status(bids_size: u32) -> BroadcastMessage {
+        BroadcastMessage::Status(BroadcastStats {
+            instance_tag: [0; 8],
+            product_id: 1,
+            bids_size,
+            ask_size: 0,
+            matched_orders: 0,
+            total_received_orders: 0,
+            start_time: 0,
+            sequence: 0,
+        })
+    }
+
+    fn parsed(input: &str) -> String {
+        parse_filter_expr(input).unwrap().to_string()
+    }
+
+    #[test]
+    fn and_binds_tighter_than_or() {
+        assert_eq!(parsed("price > 1 || price < 5 && quantity == 2"), "(price > 1 || (price < 5 && quantity == 2))");
+        assert_eq!(parsed("price > 1 && price < 5 || quantity == 2"), "((price > 1 && price < 5) || quantity == 2)");
+        assert_eq!(parsed("(price > 1 || price < 5) && quantity == 2"), "((price > 1 || price < 5) && quantity == 2)");
+        assert_eq!(parsed("!price == 1 && quantity != 2"), "(!price == 1 && quantity != 2)");
+    }
+
+    #[test]
+    fn evaluates_with_precedence() {
+        let expr = parse_filter_expr("price >= 100 && quantity > 5 || price == 1").unwrap();
+        assert!(expr.eval(&trade(100, 6)));
+        assert!(!expr.eval(&trade(100, 5)));
+        assert!(expr.eval(&trade(1, 0)));
+        assert!(!expr.eval(&trade(99, 50)));
+
+        let negated = parse_filter_expr("!(price < 100)").unwrap();
+        assert!(negated.eval(&trade(100, 1)));
+        assert!(!negated.eval(&trade(99, 1)));
+    }
+
+    #[test]
+    fn fields_missing_from_message_compare_false() {
+        let expr = parse_filter_expr("bids_size > 10").unwrap();
+        assert!(expr.eval(&status(11)));
+        assert!(!expr.eval(&trade(100, 1)));
+        // 取反后缺失字段的消息匹配
+        assert!(parse_filter_expr("!bids_size > 10").unwrap().eval(&trade(100, 1)));
+    }
+
+    #[test]
+    fn numbers_accept_underscores() {
+        let expr = parse_filter_expr("price==1_000").unwrap();
+        assert!(expr.eval(&trade(1000, 1)));
+    }
+
+    #[test]
+    fn rejects_malformed_expressions() {
+        let error = |input: &str| parse_filter_expr(input).unwrap_err();
+        assert!(error("volume > 1").starts_with("Unknown field 'volume'"));
+        assert_eq!(error("price 1"), "Expected comparison operator after 'price'");
+        assert_eq!(error("price > "), "Expected number after operator for 'price'");
+        assert_eq!(error("(price > 1"), "Expected ')'");
+        assert_eq!(error(""), "Unexpected end of expression");
+        assert_eq!(error("price > 1 &&"), "Unexpected end of expression");
+        assert!(error("price > 1 quantity > 2").starts_with("Unexpected trailing input"));
+        assert_eq!(error("price > 1 & quantity > 2"), "Unexpected character '&' at position 10");
+        assert!(error("price > 99999999999999999999").starts_with("Invalid number"));
+    }
+
+    #[test]
+    fn parses_price_ranges() {
+        assert_eq!(parse_price_range("100..200"), Ok((Some(100), Some(200))));
+        assert_eq!(parse_price_range("100.."), Ok((Some(100), None)));
+        assert_eq!(parse_price_range("..200"), Ok((None, Some(200))));
+        assert!(parse_price_range("100-200").is_err());
+        assert!(parse_price_range("a..200").is_err());
+    }
+
+    #[test]
+    fn trade_only_conditions_reject_status() {
+        let filter = MessageFilter { min_quantity: Some(5), ..MessageFilter::default() };
+        assert!(filter.matches(&trade(100, 5)));
+        assert!(!filter.matches(&trade(100, 4)));
+        assert!(!filter.matches(&status(0)));
+
+        let filter = MessageFilter { order_id: Some(22), price_range: Some((Some(50), Some(100))), ..MessageFilter::default() };
+        assert!(filter.matches(&trade(100, 1)));
+        assert!(!filter.matches(&trade(101, 1)));
+    }
+}
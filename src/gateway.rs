@@ -0,0 +1,146 @@
+// src/gateway.rs
+
+use crate::encoding::{serialize_cancel, serialize_order, BroadcastMessage};
+use crate::clock::Clock;
+use crate::journal::OrderJournal;
+use crate::position::{Fill, PositionBook};
+use crate::risk::RiskEngine;
+use crate::stops::StopBook;
+use crate::transport::{Endpoint, FrameSender};
+use crate::types::{Order, MESSAGE_TOTAL_SIZE};
+
+// 下单出口：所有订单和撤单都经过这里，发送前做风控检查
+pub struct OrderGateway {
+    sender: FrameSender,
+    endpoint: Endpoint,
+    risk: Option<RiskEngine>,
+    positions: PositionBook,
+    journal: OrderJournal,
+    stops: StopBook,
+    clock: Clock,
+}
+
+impl OrderGateway {
+    pub fn new(sender: FrameSender, endpoint: Endpoint, risk: Option<RiskEngine>, positions: PositionBook, journal: OrderJournal, clock: Clock) -> Self {
+        OrderGateway { sender, endpoint, risk, positions, journal, stops: StopBook::new(), clock }
+    }
+
+    // 下单路径的当前时间 (由 --clock 决定)
+    pub fn now(&mut self) -> Result<u64, String> {
+        self.clock.now()
+    }
+
+    pub fn endpoint(&self) -> &Endpoint {
+        &self.endpoint
+    }
+
+    pub fn describe(&self) -> String {
+        self.sender.describe()
+    }
+
+    // 最近看到的成交价 (风控状态或持仓中保存的)
+    pub fn last_trade_price(&self, product_id: u16) -> Option<u64> {
+        self.risk.as_ref()
+            .and_then(|risk| risk.last_trade_price(product_id))
+            .or_else(|| self.positions.last_price(product_id))
+    }
+
+    pub fn position(&self, product_id: u16) -> i64 {
+        self.positions.quantity(product_id)
+    }
+
+    // 风控通过后序列化并发送订单，返回发送的帧
+    pub fn submit(&mut self, order: &Order) -> Result<[u8; MESSAGE_TOTAL_SIZE], String> {
+        let now = self.clock.now()?;
+        if let Some(risk) = self.risk.as_mut() {
+            risk.check(order, now).map_err(|violation| violation.to_string())?;
+        }
+
+        let frame = serialize_order(order);
+        self.sender.send_frame(&frame)?;
+        self.journal.record_submit(now, &frame)?;
+
+        if let Some(risk) = self.risk.as_mut() {
+            risk.record_submit(order, now);
+            risk.save()?;
+        }
+        self.positions.record_submit(order, now);
+        self.positions.save_if_due()?;
+        Ok(frame)
+    }
+
+    // 原始帧：按原样发送，不做风控检查，也不写订单日志 (协议调试用)
+    pub fn send_raw(&mut self, frame: &[u8]) -> Result<(), String> {
+        self.sender.send_frame(frame)
+    }
+
+    // 客户端合成止损：先做风控检查，挂在本地等待行情触发
+    pub fn hold_stop(&mut self, order: Order) -> Result<(), String> {
+        let now = self.clock.now()?;
+        if let Some(risk) = self.risk.as_mut() {
+            risk.check(&order, now).map_err(|violation| violation.to_string())?;
+        }
+        self.stops.hold(order);
+        Ok(())
+    }
+
+    pub fn pending_stops(&self) -> usize {
+        self.stops.len()
+    }
+
+    // 检查成交是否触发本地止损，返回被触发的订单 (由调用方发送)
+    pub fn trigger_stops(&mut self, message: &BroadcastMessage, triggered: &mut Vec<Order>) -> Result<(), String> {
+        if !self.stops.is_empty() {
+            let now = self.clock.now()?;
+            self.stops.check(message, now, triggered);
+        }
+        Ok(())
+    }
+
+    pub fn cancel(&mut self, order_id: u64) -> Result<[u8; MESSAGE_TOTAL_SIZE], String> {
+        let now = self.clock.now()?;
+        let frame = serialize_cancel(order_id);
+        self.sender.send_frame(&frame)?;
+        self.journal.record_cancel(now, &frame)?;
+
+        if let Some(risk) = self.risk.as_mut() {
+            risk.record_cancel(order_id, now);
+            risk.save()?;
+        }
+        self.positions.record_cancel(order_id, now);
+        self.positions.save_if_due()?;
+        Ok(frame)
+    }
+
+    // 行情回报：更新最近成交价和自己订单的成交，返回自己订单的成交 (同时写入订单日志)
+    pub fn observe(&mut self, message: &BroadcastMessage, fills: &mut Vec<Fill>) -> Result<(), String> {
+        if let Some(risk) = self.risk.as_mut() {
+            risk.observe(message);
+        }
+        let first = fills.len();
+        self.positions.observe(message, fills);
+        if fills.len() > first {
+            let now = self.clock.now()?;
+            for fill in &fills[first..] {
+                self.journal.record_fill(now, fill)?;
+            }
+            self.positions.save_if_due()?;
+        }
+        Ok(())
+    }
+
+    // 退出前持久化状态
+    pub fn close(&mut self) -> Result<(), String> {
+        if let Some(risk) = self.risk.as_ref() {
+            risk.save()?;
+        }
+        self.positions.save()
+    }
+
+    // 会话结束时打印持仓 (没有持仓时不打印)
+    pub fn print_positions(&self) {
+        if !self.positions.is_empty() {
+            self.positions.print();
+        }
+    }
+}
@@ -0,0 +1,113 @@
+// src/hexdump.rs
+
+use std::io::IsTerminal;
+
+use crate::layout::{frame_layout, print_annotated, Field, FieldKind};
+use crate::params::FrameDisplay;
+use crate::types::MESSAGE_TOTAL_SIZE;
+
+// --- 帧的十六进制显示 ---
+//
+// 0000  61 01 00 01 18 df 95 b9  58 51 f0 00 00 00 00 00  |a.......XQ......|
+// 0010  ...
+//   [0] checksum  [1] msg_type  [2] product_id  [4] order_id  ...
+//
+// 每个字段一种颜色 (按 Order/MatchResult/BroadcastStats 布局)，空闲字节为灰色；
+// 图例给出每个字段的起始偏移，不着色时也能看出字段边界
+
+const BYTES_PER_ROW: usize = 16;
+const LEGEND_WIDTH: usize = 78;
+const PALETTE: [&str; 6] = ["36", "33", "32", "35", "34", "31"];
+const RESERVED_COLOR: &str = "90";
+
+// 输出到终端且未设置 NO_COLOR 时着色
+pub fn use_color(stream: &impl IsTerminal) -> bool {
+    stream.is_terminal() && std::env::var_os("NO_COLOR").is_none()
+}
+
+fn paint(text: &str, color: Option<&str>) -> String {
+    match color {
+        Some(code) => format!("\x1b[{}m{}\x1b[0m", code, text),
+        None => text.to_string(),
+    }
+}
+
+// 每个字节所属字段的颜色
+fn byte_colors(fields: &[Field], len: usize) -> Vec<&'static str> {
+    let mut colors = vec![RESERVED_COLOR; len];
+    let mut next = 0;
+    for field in fields {
+        if field.kind == FieldKind::Reserved {
+            continue;
+        }
+        let color = PALETTE[next % PALETTE.len()];
+        next += 1;
+        for slot in colors.iter_mut().skip(field.offset).take(field.len) {
+            *slot = color;
+        }
+    }
+    colors
+}
+
+// 渲染十六进制显示 (含图例)。长度不是 MESSAGE_TOTAL_SIZE 的帧没有字段信息，只按字节显示
+pub fn render(frame: &[u8], color: bool) -> String {
+    let fields = <&[u8; MESSAGE_TOTAL_SIZE]>::try_from(frame)
+        .map(frame_layout)
+        .unwrap_or_default();
+    let colors = byte_colors(&fields, frame.len());
+    let color_of = |i: usize| (color && !fields.is_empty()).then_some(colors[i]);
+
+    let mut out = String::new();
+    for (row, chunk) in frame.chunks(BYTES_PER_ROW).enumerate() {
+        let start = row * BYTES_PER_ROW;
+        out += &format!("{:04x} ", start);
+        for column in 0..BYTES_PER_ROW {
+            if column == BYTES_PER_ROW / 2 {
+                out.push(' ');
+            }
+            match chunk.get(column) {
+                Some(byte) => out += &format!(" {}", paint(&format!("{:02x}", byte), color_of(start + column))),
+                None => out += "   ",
+            }
+        }
+        let ascii: String = chunk.iter().enumerate()
+            .map(|(column, &byte)| {
+                let c = if byte.is_ascii_graphic() { byte as char } else { '.' };
+                paint(&c.to_string(), color_of(start + column))
+            })
+            .collect();
+        out += &format!("  |{}|\n", ascii);
+    }
+
+    // 图例：[偏移] 字段名，按宽度换行
+    let mut line = String::new();
+    let mut width = 0;
+    for field in fields.iter().filter(|field| field.kind != FieldKind::Reserved) {
+        let entry = format!("[{}] {}", field.offset, field.name);
+        if width > 0 && width + entry.len() + 2 > LEGEND_WIDTH {
+            out += &format!("{}\n", line);
+            line.clear();
+            width = 0;
+        }
+        line += "  ";
+        line += &paint(&entry, color_of(field.offset));
+        width += entry.len() + 2;
+    }
+    if width > 0 {
+        out += &format!("{}\n", line);
+    }
+    out
+}
+
+// 按 --frames 级别打印一个发送的帧：none 不显示，hex 十六进制，fields 再加逐字段解码
+pub fn print_frame(title: &str, frame: &[u8], display: FrameDisplay) {
+    if display == FrameDisplay::None {
+        return;
+    }
+    println!("{} ({} bytes):", title, frame.len());
+    print!("{}", render(frame, use_color(&std::io::stdout())));
+    if display == FrameDisplay::Fields
+        && let Ok(frame) = <&[u8; MESSAGE_TOTAL_SIZE]>::try_from(frame) {
+        print_annotated(frame);
+    }
+}
@@ -0,0 +1,211 @@
+// src/instance.rs
+
+use std::collections::BTreeMap;
+use std::fmt;
+
+use crate::encoding::BroadcastMessage;
+use crate::types::format_instance_tag;
+
+// 某个产品上观察到的一个引擎实例
+#[derive(Debug, Default)]
+struct InstanceState {
+    start_time: Option<u64>, // 最近一次 BroadcastStats.start_time
+    frames: u64,
+    restarts: u64,
+}
+
+#[derive(Debug, Default)]
+struct ProductInstances {
+    instances: BTreeMap<[u8; 8], InstanceState>,
+    latest: Option<[u8; 8]>, // 最近发送消息的实例
+}
+
+// 实例变化告警
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum InstanceEvent {
+    // 产品上出现了新的 instance_tag (previous 为此前活跃的实例)
+    NewInstance { product_id: u16, instance_tag: [u8; 8], previous: Option<[u8; 8]> },
+    // 同一 instance_tag 的 start_time 变化：引擎重启
+    Restart { product_id: u16, instance_tag: [u8; 8], old_start_time: u64, new_start_time: u64 },
+}
+
+impl fmt::Display for InstanceEvent {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            InstanceEvent::NewInstance { product_id, instance_tag, previous: Some(previous) } =>
+                write!(f, "🚨 FAILOVER: Product={} | New Instance={} | Previous={}",
+                    product_id, format_instance_tag(instance_tag), format_instance_tag(previous)),
+            InstanceEvent::NewInstance { product_id, instance_tag, previous: None } =>
+                write!(f, "🆕 INSTANCE: Product={} | Instance={}", product_id, format_instance_tag(instance_tag)),
+            InstanceEvent::Restart { product_id, instance_tag, old_start_time, new_start_time } =>
+                write!(f, "🚨 ENGINE RESTART: Product={} | Instance={} | StartTime {} -> {}",
+                    product_id, format_instance_tag(instance_tag), old_start_time, new_start_time),
+        }
+    }
+}
+
+// 按 product_id 跟踪活跃的引擎实例，检测新实例 (切换) 和重启；
+// 可选只放行主实例的消息 (A/B 切换期间)
+#[derive(Debug, Default)]
+pub struct InstanceTracker {
+    products: BTreeMap<u16, ProductInstances>,
+    primary: Option<[u8; 8]>,
+    filtered: u64,
+}
+
+impl InstanceTracker {
+    pub fn new(primary: Option<[u8; 8]>) -> Self {
+        InstanceTracker { primary, ..Default::default() }
+    }
+
+    // 记录一条消息，返回需要告警的事件
+    pub fn observe(&mut self, message: &BroadcastMessage) -> Option<InstanceEvent> {
+        let (product_id, start_time) = match message {
+            BroadcastMessage::Trade(result) => (result.product_id, None),
+            BroadcastMessage::Status(stats) => (stats.product_id, Some(stats.start_time)),
+        };
+        let instance_tag = *message.instance_tag();
+        let product = self.products.entry(product_id).or_default();
+
+        let is_new = !product.instances.contains_key(&instance_tag);
+        let previous = product.latest.replace(instance_tag);
+        let state = product.instances.entry(instance_tag).or_default();
+        state.frames += 1;
+
+        if is_new {
+            state.start_time = start_time;
+            return Some(InstanceEvent::NewInstance { product_id, instance_tag, previous });
+        }
+
+        match (state.start_time, start_time) {
+            (Some(old_start_time), Some(new_start_time)) if old_start_time != new_start_time => {
+                state.start_time = Some(new_start_time);
+                state.restarts += 1;
+                Some(InstanceEvent::Restart { product_id, instance_tag, old_start_time, new_start_time })
+            }
+            (None, Some(new_start_time)) => {
+                state.start_time = Some(new_start_time);
+                None
+            }
+            _ => None,
+        }
+    }
+
+    // 是否放行：未指定主实例时全部放行
+    pub fn accepts(&mut self, message: &BroadcastMessage) -> bool {
+        match &self.primary {
+            Some(primary) if primary != message.instance_tag() => {
+                self.filtered += 1;
+                false
+            }
+            _ => true,
+        }
+    }
+
+    pub fn print(&self) {
+        if self.products.is_empty() {
+            return;
+        }
+        println!("Instances by product:");
+        for (product_id, product) in &self.products {
+            for (tag, state) in &product.instances {
+                let active = if product.latest == Some(*tag) { " (active)" } else { "" };
+                println!("  Product {}: Instance={}{} | Frames={} | Restarts={} | StartTime={}",
+                    product_id, format_instance_tag(tag), active, state.frames, state.restarts,
+                    state.start_time.map_or("-".to_string(), |t| t.to_string()));
+            }
+        }
+        if let Some(primary) = &self.primary {
+            println!("  Primary instance {}: filtered {} frames from other instances",
+                format_instance_tag(primary), self.filtered);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{BroadcastStats, MatchResult};
+
+    const A: [u8; 8] = *b"ENGINE-A";
+    const B: [u8; 8] = *b"ENGINE-B";
+
+    fn stats(instance_tag: [u8; 8], product_id: u16, start_time: u64) -> BroadcastMessage {
+        BroadcastMessage::Status(BroadcastStats {
+            instance_tag,
+            product_id,
+            bids_size: 0,
+            ask_size: 0,
+            matched_orders: 0,
+            total_received_orders: 0,
+            start_time,
+            sequence: 0,
+        })
+    }
+
+    fn trade(instance_tag: [u8; 8], product_id: u16) -> BroadcastMessage {
+        BroadcastMessage::Trade(MatchResult {
+            instance_tag,
+            product_id,
+            buy_order_id: 1,
+            sell_order_id: 2,
+            price: 100,
+            quantity: 1,
+            trade_network_time: 0,
+            internal_match_time: 0,
+            sequence: 0,
+        })
+    }
+
+    #[test]
+    fn new_instance_on_product_is_failover() {
+        let mut tracker = InstanceTracker::new(None);
+        assert_eq!(tracker.observe(&stats(A, 1, 1000)),
+            Some(InstanceEvent::NewInstance { product_id: 1, instance_tag: A, previous: None }));
+        assert_eq!(tracker.observe(&trade(A, 1)), None);
+        assert_eq!(tracker.observe(&trade(B, 1)),
+            Some(InstanceEvent::NewInstance { product_id: 1, instance_tag: B, previous: Some(A) }));
+        // 切回已知实例不再告警
+        assert_eq!(tracker.observe(&trade(A, 1)), None);
+        // 产品分别跟踪
+        assert_eq!(tracker.observe(&trade(B, 2)),
+            Some(InstanceEvent::NewInstance { product_id: 2, instance_tag: B, previous: None }));
+
+        let product = &tracker.products[&1];
+        assert_eq!(product.latest, Some(A));
+        assert_eq!((product.instances[&A].frames, product.instances[&B].frames), (3, 1));
+    }
+
+    #[test]
+    fn changed_start_time_is_restart() {
+        let mut tracker = InstanceTracker::new(None);
+        tracker.observe(&stats(A, 1, 5000));
+        assert_eq!(tracker.observe(&stats(A, 1, 5000)), None);
+        assert_eq!(tracker.observe(&stats(A, 1, 3000)),
+            Some(InstanceEvent::Restart { product_id: 1, instance_tag: A, old_start_time: 5000, new_start_time: 3000 }));
+        assert_eq!(tracker.observe(&stats(A, 1, 3000)), None);
+        assert_eq!(tracker.products[&1].instances[&A].restarts, 1);
+    }
+
+    #[test]
+    fn start_time_learned_after_first_trade() {
+        let mut tracker = InstanceTracker::new(None);
+        tracker.observe(&trade(A, 1));
+        // 先收到成交时没有 start_time，第一条统计只记录不告警
+        assert_eq!(tracker.observe(&stats(A, 1, 5000)), None);
+        assert!(matches!(tracker.observe(&stats(A, 1, 6000)), Some(InstanceEvent::Restart { .. })));
+    }
+
+    #[test]
+    fn primary_instance_filters_others() {
+        let mut tracker = InstanceTracker::new(Some(A));
+        assert!(tracker.accepts(&trade(A, 1)));
+        assert!(!tracker.accepts(&trade(B, 1)));
+        assert!(!tracker.accepts(&stats(B, 2, 1000)));
+        assert_eq!(tracker.filtered, 2);
+
+        let mut tracker = InstanceTracker::new(None);
+        assert!(tracker.accepts(&trade(B, 1)));
+        assert_eq!(tracker.filtered, 0);
+    }
+}
@@ -0,0 +1,244 @@
+// src/journal.rs
+
+use std::fs::{self, File, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use crate::encoding::{decode_hex, deserialize_cancel, deserialize_order, encode_hex};
+use crate::hexdump::print_frame;
+use crate::params::FrameDisplay;
+use crate::position::Fill;
+use crate::tif::format_local_time;
+use crate::types::{Order, MESSAGE_TOTAL_SIZE, ORDER_TYPE_BUY};
+
+// --- 订单日志 (只追加) ---
+//
+// 每行一条记录，字段以 Tab 分隔：
+//   SUBMIT <发送时间 ns> <50 字节帧的十六进制>
+//   CANCEL <发送时间 ns> <50 字节帧的十六进制>
+//   FILL   <观察时间 ns> <order_id> <price> <quantity>
+
+const KIND_SUBMIT: &str = "SUBMIT";
+const KIND_CANCEL: &str = "CANCEL";
+const KIND_FILL: &str = "FILL";
+
+#[derive(Debug, Clone)]
+pub enum JournalEntry {
+    Submit { sent_at: u64, frame: Vec<u8>, order: Order },
+    Cancel { sent_at: u64, frame: Vec<u8>, order_id: u64 },
+    Fill { observed_at: u64, order_id: u64, price: u64, quantity: u32 },
+}
+
+pub struct OrderJournal {
+    file: File,
+    path: PathBuf,
+}
+
+impl OrderJournal {
+    pub fn open(path: PathBuf) -> Result<Self, String> {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)
+                .map_err(|e| format!("Failed to create state directory {}: {}", dir.display(), e))?;
+        }
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&path)
+            .map_err(|e| format!("Failed to open order journal {}: {}", path.display(), e))?;
+        Ok(OrderJournal { file, path })
+    }
+
+    fn append(&mut self, line: String) -> Result<(), String> {
+        writeln!(self.file, "{}", line)
+            .and_then(|_| self.file.flush())
+            .map_err(|e| format!("Failed to write order journal {}: {}", self.path.display(), e))
+    }
+
+    pub fn record_submit(&mut self, sent_at: u64, frame: &[u8]) -> Result<(), String> {
+        self.append(format!("{}\t{}\t{}", KIND_SUBMIT, sent_at, encode_hex(frame)))
+    }
+
+    pub fn record_cancel(&mut self, sent_at: u64, frame: &[u8]) -> Result<(), String> {
+        self.append(format!("{}\t{}\t{}", KIND_CANCEL, sent_at, encode_hex(frame)))
+    }
+
+    pub fn record_fill(&mut self, observed_at: u64, fill: &Fill) -> Result<(), String> {
+        self.append(format!("{}\t{}\t{}\t{}\t{}", KIND_FILL, observed_at, fill.order_id, fill.price, fill.quantity))
+    }
+}
+
+fn parse_line(line: &str) -> Result<JournalEntry, String> {
+    let fields: Vec<&str> = line.split('\t').collect();
+    let number = |i: usize| -> Result<u64, String> {
+        fields.get(i)
+            .ok_or_else(|| "missing field".to_string())?
+            .parse::<u64>()
+            .map_err(|e| e.to_string())
+    };
+    let frame = |i: usize| -> Result<Vec<u8>, String> {
+        let frame = decode_hex(fields.get(i).ok_or_else(|| "missing frame".to_string())?)?;
+        if frame.len() != MESSAGE_TOTAL_SIZE {
+            return Err(format!("frame has {} bytes, expected {}", frame.len(), MESSAGE_TOTAL_SIZE));
+        }
+        Ok(frame)
+    };
+
+    match fields[0] {
+        KIND_SUBMIT => {
+            let frame = frame(2)?;
+            let order = deserialize_order(&frame)?;
+            Ok(JournalEntry::Submit { sent_at: number(1)?, frame, order })
+        }
+        KIND_CANCEL => {
+            let frame = frame(2)?;
+            let order_id = deserialize_cancel(&frame)?;
+            Ok(JournalEntry::Cancel { sent_at: number(1)?, frame, order_id })
+        }
+        KIND_FILL => Ok(JournalEntry::Fill {
+            observed_at: number(1)?,
+            order_id: number(2)?,
+            price: number(3)?,
+            quantity: number(4)?.try_into().map_err(|_| "quantity out of range".to_string())?,
+        }),
+        other => Err(format!("unknown record type {}", other)),
+    }
+}
+
+pub fn read_journal(path: &Path) -> Result<Vec<JournalEntry>, String> {
+    let text = match fs::read_to_string(path) {
+        Ok(text) => text,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(format!("Failed to read order journal {}: {}", path.display(), e)),
+    };
+    text.lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .map(|(i, line)| parse_line(line)
+            .map_err(|e| format!("Invalid order journal {} line {}: {}", path.display(), i + 1, e)))
+        .collect()
+}
+
+// 单个订单的汇总：提交记录 + 撤单 + 成交
+struct OrderHistory<'a> {
+    sent_at: u64,
+    frame: &'a [u8],
+    order: &'a Order,
+    cancels: Vec<(u64, &'a [u8])>,
+    fills: Vec<(u64, u64, u32)>,
+}
+
+impl OrderHistory<'_> {
+    fn filled(&self) -> u64 {
+        self.fills.iter().map(|&(_, _, quantity)| u64::from(quantity)).sum()
+    }
+
+    fn status(&self, now: u64) -> &'static str {
+        let filled = self.filled();
+        let expired = self.order.is_immediate() || (self.order.expire_time != 0 && self.order.expire_time <= now);
+        if filled >= u64::from(self.order.quantity) {
+            "FILLED"
+        } else if !self.cancels.is_empty() {
+            "CANCELLED"
+        } else if expired {
+            "EXPIRED"
+        } else if filled > 0 {
+            "PARTIAL"
+        } else {
+            "OPEN"
+        }
+    }
+}
+
+fn collect_orders(entries: &[JournalEntry]) -> Vec<OrderHistory<'_>> {
+    let mut orders: Vec<OrderHistory> = entries.iter()
+        .filter_map(|entry| match entry {
+            JournalEntry::Submit { sent_at, frame, order } => Some(OrderHistory {
+                sent_at: *sent_at,
+                frame,
+                order,
+                cancels: Vec::new(),
+                fills: Vec::new(),
+            }),
+            _ => None,
+        })
+        .collect();
+
+    for entry in entries {
+        match entry {
+            JournalEntry::Cancel { sent_at, frame, order_id } => {
+                if let Some(history) = orders.iter_mut().find(|h| h.order.order_id == *order_id) {
+                    history.cancels.push((*sent_at, frame));
+                }
+            }
+            JournalEntry::Fill { observed_at, order_id, price, quantity } => {
+                if let Some(history) = orders.iter_mut().find(|h| h.order.order_id == *order_id) {
+                    history.fills.push((*observed_at, *price, *quantity));
+                }
+            }
+            JournalEntry::Submit { .. } => {}
+        }
+    }
+    orders
+}
+
+fn side_name(order: &Order) -> &'static str {
+    if order.order_type == ORDER_TYPE_BUY { "BUY" } else { "SELL" }
+}
+
+// orders list：每个订单一行
+pub fn print_order_list(entries: &[JournalEntry], now: u64) {
+    let orders = collect_orders(entries);
+    if orders.is_empty() {
+        println!("(no orders in journal)");
+        return;
+    }
+
+    println!("{:>20} {:>20} {:>8} {:>5} {:>10} {:>4} {:>12} {:>10} {:>10} {:>10}",
+        "OrderID", "SentAt(ns)", "Product", "Side", "Type", "TIF", "Price", "Quantity", "Filled", "Status");
+    for h in &orders {
+        println!("{:>20} {:>20} {:>8} {:>5} {:>10} {:>4} {:>12} {:>10} {:>10} {:>10}",
+            h.order.order_id, h.sent_at, h.order.product_id, side_name(h.order), h.order.price_type_name(),
+            h.order.time_in_force_name(), h.order.price, h.order.quantity, h.filled(), h.status(now));
+    }
+}
+
+// orders show <id>：订单明细、原始帧、撤单和成交
+pub fn print_order(entries: &[JournalEntry], order_id: u64, now: u64, frames: FrameDisplay) -> Result<(), String> {
+    let orders = collect_orders(entries);
+    let h = orders.iter()
+        .find(|h| h.order.order_id == order_id)
+        .ok_or_else(|| format!("Order {} not found in journal", order_id))?;
+
+    println!("--- Order {} ---", order_id);
+    println!("Status: {}", h.status(now));
+    println!("Sent At: {} ns", h.sent_at);
+    println!("Product ID: {}", h.order.product_id);
+    println!("Side: {} | Type: {}", side_name(h.order), h.order.price_type_name());
+    println!("Price: {}, Quantity: {}", h.order.price, h.order.quantity);
+    println!("Time In Force: {}", h.order.time_in_force_name());
+    if h.order.is_stop() {
+        println!("Trigger Price: {}", h.order.trigger_price);
+    }
+    if h.order.is_iceberg() {
+        println!("Display Quantity: {}", h.order.display_quantity);
+    }
+    if h.order.is_post_only() {
+        println!("Post Only: true");
+    }
+    println!("Submit Time: {} ({})", h.order.submit_time, format_local_time(h.order.submit_time));
+    if h.order.expire_time != 0 {
+        println!("Expire Time: {} ({})", h.order.expire_time, format_local_time(h.order.expire_time));
+    }
+    print_frame("Serialized Message", h.frame, frames);
+
+    for (sent_at, frame) in &h.cancels {
+        println!("Cancel Sent At: {} ns", sent_at);
+        print_frame("Serialized Message", frame, frames);
+    }
+
+    println!("Fills: {} (filled {} of {})", h.fills.len(), h.filled(), h.order.quantity);
+    for (observed_at, price, quantity) in &h.fills {
+        println!("  {} ns | Price={} | Qty={}", observed_at, price, quantity);
+    }
+    Ok(())
+}
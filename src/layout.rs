@@ -0,0 +1,208 @@
+// src/layout.rs
+
+use crate::encoding::{calculate_checksum, decode_hex, encode_hex};
+use crate::tif::format_local_time;
+use crate::types::{format_instance_tag, message_type_name, MESSAGE_TOTAL_SIZE, ORDER_EXTENSION_OFFSET, SEQUENCE_OFFSET};
+use crate::types::{MSG_ORDER_CANCEL, MSG_ORDER_SUBMIT, MSG_RETRANSMIT_REQUEST, MSG_STATUS_BROADCAST, MSG_TRADE_BROADCAST};
+use crate::types::{price_type_flag_names, price_type_name, ORDER_FLAG_ICEBERG, ORDER_PRICE_TYPE_MASK};
+use crate::types::{ORDER_PRICE_TYPE_STOP, ORDER_PRICE_TYPE_STOP_LIMIT, ORDER_TYPE_BUY, ORDER_TYPE_SELL};
+use crate::types::{ORDER_PRODUCT_ID_OFFSET, ORDER_ID_OFFSET, ORDER_PRICE_OFFSET, ORDER_QUANTITY_OFFSET, ORDER_SIDE_OFFSET};
+use crate::types::{ORDER_PRICE_TYPE_OFFSET, ORDER_SUBMIT_TIME_OFFSET, ORDER_EXPIRE_TIME_OFFSET, CANCEL_ORDER_ID_OFFSET};
+use crate::types::{RETRANSMIT_INSTANCE_TAG_OFFSET, RETRANSMIT_START_SEQUENCE_OFFSET, RETRANSMIT_END_SEQUENCE_OFFSET};
+use crate::types::{TRADE_INSTANCE_TAG_OFFSET, TRADE_PRODUCT_ID_OFFSET, TRADE_BUY_ORDER_ID_OFFSET, TRADE_SELL_ORDER_ID_OFFSET};
+use crate::types::{TRADE_PRICE_OFFSET, TRADE_QUANTITY_OFFSET, TRADE_NETWORK_TIME_OFFSET, TRADE_INTERNAL_MATCH_TIME_OFFSET};
+use crate::types::{STATS_INSTANCE_TAG_OFFSET, STATS_PRODUCT_ID_OFFSET, STATS_BIDS_SIZE_OFFSET, STATS_ASK_SIZE_OFFSET};
+use crate::types::{STATS_MATCHED_ORDERS_OFFSET, STATS_TOTAL_RECEIVED_ORDERS_OFFSET, STATS_START_TIME_OFFSET};
+
+// --- 帧字段布局 ---
+// 偏移取自 types.rs 的 *_OFFSET 常量 (encoding.rs 同样使用)，供原始帧解码和十六进制显示使用
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FieldKind {
+    Checksum,
+    MessageType,
+    U16,
+    U32,
+    U64,
+    Timestamp, // u64 Unix 纳秒
+    Side,
+    PriceType,
+    InstanceTag,
+    Reserved, // 未使用的字节
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct Field {
+    pub offset: usize,
+    pub len: usize,
+    pub name: &'static str,
+    pub kind: FieldKind,
+}
+
+const fn field(offset: usize, len: usize, name: &'static str, kind: FieldKind) -> Field {
+    Field { offset, len, name, kind }
+}
+
+const HEADER: [Field; 2] = [
+    field(0, 1, "checksum", FieldKind::Checksum),
+    field(1, 1, "msg_type", FieldKind::MessageType),
+];
+
+const ORDER_FIELDS: [Field; 8] = [
+    field(ORDER_PRODUCT_ID_OFFSET, 2, "product_id", FieldKind::U16),
+    field(ORDER_ID_OFFSET, 8, "order_id", FieldKind::U64),
+    field(ORDER_PRICE_OFFSET, 8, "price", FieldKind::U64),
+    field(ORDER_QUANTITY_OFFSET, 4, "quantity", FieldKind::U32),
+    field(ORDER_SIDE_OFFSET, 1, "order_type", FieldKind::Side),
+    field(ORDER_PRICE_TYPE_OFFSET, 1, "price_type", FieldKind::PriceType),
+    field(ORDER_SUBMIT_TIME_OFFSET, 8, "submit_time", FieldKind::Timestamp),
+    field(ORDER_EXPIRE_TIME_OFFSET, 8, "expire_time", FieldKind::Timestamp),
+];
+
+const CANCEL_FIELDS: [Field; 1] = [
+    field(CANCEL_ORDER_ID_OFFSET, 8, "order_id", FieldKind::U64),
+];
+
+const RETRANSMIT_FIELDS: [Field; 3] = [
+    field(RETRANSMIT_INSTANCE_TAG_OFFSET, 8, "instance_tag", FieldKind::InstanceTag),
+    field(RETRANSMIT_START_SEQUENCE_OFFSET, 2, "start_sequence", FieldKind::U16),
+    field(RETRANSMIT_END_SEQUENCE_OFFSET, 2, "end_sequence", FieldKind::U16),
+];
+
+const MATCH_RESULT_FIELDS: [Field; 8] = [
+    field(TRADE_INSTANCE_TAG_OFFSET, 8, "instance_tag", FieldKind::InstanceTag),
+    field(TRADE_PRODUCT_ID_OFFSET, 2, "product_id", FieldKind::U16),
+    field(TRADE_BUY_ORDER_ID_OFFSET, 8, "buy_order_id", FieldKind::U64),
+    field(TRADE_SELL_ORDER_ID_OFFSET, 8, "sell_order_id", FieldKind::U64),
+    field(TRADE_PRICE_OFFSET, 8, "price", FieldKind::U64),
+    field(TRADE_QUANTITY_OFFSET, 4, "quantity", FieldKind::U32),
+    field(TRADE_NETWORK_TIME_OFFSET, 4, "trade_network_time", FieldKind::U32),
+    field(TRADE_INTERNAL_MATCH_TIME_OFFSET, 4, "internal_match_time", FieldKind::U32),
+];
+
+const STATS_FIELDS: [Field; 7] = [
+    field(STATS_INSTANCE_TAG_OFFSET, 8, "instance_tag", FieldKind::InstanceTag),
+    field(STATS_PRODUCT_ID_OFFSET, 2, "product_id", FieldKind::U16),
+    field(STATS_BIDS_SIZE_OFFSET, 4, "bids_size", FieldKind::U32),
+    field(STATS_ASK_SIZE_OFFSET, 4, "ask_size", FieldKind::U32),
+    field(STATS_MATCHED_ORDERS_OFFSET, 4, "matched_orders", FieldKind::U32),
+    field(STATS_TOTAL_RECEIVED_ORDERS_OFFSET, 4, "total_received_orders", FieldKind::U32),
+    field(STATS_START_TIME_OFFSET, 8, "start_time", FieldKind::Timestamp),
+];
+
+// 按消息类型列出帧的全部字段 (覆盖 0..MESSAGE_TOTAL_SIZE，空闲字节标为 reserved)
+pub fn frame_layout(frame: &[u8; MESSAGE_TOTAL_SIZE]) -> Vec<Field> {
+    let mut fields = HEADER.to_vec();
+    match frame[1] {
+        MSG_ORDER_SUBMIT => {
+            fields.extend(ORDER_FIELDS);
+            // 扩展字段由 price_type 决定
+            let price_type = frame[ORDER_PRICE_TYPE_OFFSET];
+            let base = price_type & ORDER_PRICE_TYPE_MASK;
+            if matches!(base, ORDER_PRICE_TYPE_STOP | ORDER_PRICE_TYPE_STOP_LIMIT) {
+                fields.push(field(ORDER_EXTENSION_OFFSET, 8, "trigger_price", FieldKind::U64));
+            } else if price_type & ORDER_FLAG_ICEBERG != 0 {
+                fields.push(field(ORDER_EXTENSION_OFFSET, 4, "display_quantity", FieldKind::U32));
+            }
+        }
+        MSG_ORDER_CANCEL => fields.extend(CANCEL_FIELDS),
+        MSG_RETRANSMIT_REQUEST => fields.extend(RETRANSMIT_FIELDS),
+        MSG_TRADE_BROADCAST => {
+            fields.extend(MATCH_RESULT_FIELDS);
+            fields.push(field(SEQUENCE_OFFSET, 2, "sequence", FieldKind::U16));
+        }
+        MSG_STATUS_BROADCAST => {
+            fields.extend(STATS_FIELDS);
+            fields.push(field(SEQUENCE_OFFSET, 2, "sequence", FieldKind::U16));
+        }
+        // 未知类型：整个负载作为一个字段
+        _ => fields.push(field(2, MESSAGE_TOTAL_SIZE - 2, "payload", FieldKind::Reserved)),
+    }
+
+    // 字段之间和末尾的空闲字节
+    let mut layout = Vec::with_capacity(fields.len() + 2);
+    let mut next = 0;
+    for f in fields {
+        if f.offset > next {
+            layout.push(field(next, f.offset - next, "reserved", FieldKind::Reserved));
+        }
+        next = f.offset + f.len;
+        layout.push(f);
+    }
+    if next < MESSAGE_TOTAL_SIZE {
+        layout.push(field(next, MESSAGE_TOTAL_SIZE - next, "reserved", FieldKind::Reserved));
+    }
+    layout
+}
+
+fn unsigned(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0, |acc, &b| (acc << 8) | u64::from(b))
+}
+
+fn price_type_value(value: u8) -> String {
+    let flags = price_type_flag_names(value);
+    if flags.is_empty() {
+        format!("{} ({})", value, price_type_name(value))
+    } else {
+        format!("{} ({} | {})", value, price_type_name(value), flags.join(" | "))
+    }
+}
+
+// 字段值的可读形式
+pub fn field_value(frame: &[u8; MESSAGE_TOTAL_SIZE], f: &Field) -> String {
+    let bytes = &frame[f.offset..f.offset + f.len];
+    match f.kind {
+        FieldKind::Checksum => {
+            let expected = calculate_checksum(frame);
+            if frame[0] == expected {
+                format!("0x{:02x} (OK)", frame[0])
+            } else {
+                format!("0x{:02x} (MISMATCH, expected 0x{:02x})", frame[0], expected)
+            }
+        }
+        FieldKind::MessageType => format!("{} ({})", frame[1], message_type_name(frame[1])),
+        FieldKind::U16 | FieldKind::U32 | FieldKind::U64 => unsigned(bytes).to_string(),
+        FieldKind::Timestamp => match unsigned(bytes) {
+            0 => "0".to_string(),
+            nanos => format!("{} ({})", nanos, format_local_time(nanos)),
+        },
+        FieldKind::Side => match bytes[0] {
+            ORDER_TYPE_BUY => format!("{} (BUY)", bytes[0]),
+            ORDER_TYPE_SELL => format!("{} (SELL)", bytes[0]),
+            other => format!("{} (UNKNOWN)", other),
+        },
+        FieldKind::PriceType => price_type_value(bytes[0]),
+        FieldKind::InstanceTag => {
+            let tag: [u8; 8] = bytes.try_into().unwrap_or_default();
+            format_instance_tag(&tag)
+        }
+        FieldKind::Reserved => {
+            if bytes.iter().all(|&b| b == 0) { "-".to_string() } else { "(non-zero)".to_string() }
+        }
+    }
+}
+
+// 逐字段注释输出：偏移、长度、字段名、原始字节、值
+pub fn print_annotated(frame: &[u8; MESSAGE_TOTAL_SIZE]) {
+    println!("--- {} ({} bytes) ---", message_type_name(frame[1]), MESSAGE_TOTAL_SIZE);
+    println!("{:>6} {:>4}  {:<22} {:<24} Value", "Offset", "Len", "Field", "Bytes");
+    for f in frame_layout(frame) {
+        let bytes = &frame[f.offset..f.offset + f.len];
+        // 较长的空闲区只显示前 8 字节
+        let hex = if bytes.len() > 8 {
+            format!("{}..", encode_hex(&bytes[..8]))
+        } else {
+            encode_hex(bytes)
+        };
+        println!("{:>6} {:>4}  {:<22} {:<24} {}", f.offset, f.len, f.name, hex, field_value(frame, &f));
+    }
+}
+
+// 解析命令行给出的十六进制帧 (可带 0x 前缀和空白)，长度必须为 MESSAGE_TOTAL_SIZE
+pub fn parse_frame_hex(text: &str) -> Result<[u8; MESSAGE_TOTAL_SIZE], String> {
+    let text = text.trim();
+    let text = text.strip_prefix("0x").or_else(|| text.strip_prefix("0X")).unwrap_or(text);
+    let bytes = decode_hex(text)?;
+    bytes.as_slice().try_into().map_err(|_| format!(
+        "Frame has {} bytes, expected {} ({} hex digits)", bytes.len(), MESSAGE_TOTAL_SIZE, MESSAGE_TOTAL_SIZE * 2))
+}
@@ -0,0 +1,221 @@
+// src/listener.rs
+
+use std::io::{self, Read};
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+use std::time::{Duration, Instant};
+
+use mio::net::{TcpStream, UdpSocket};
+use mio::{Events, Interest, Poll, Token};
+
+use crate::encoding::BroadcastMessage;
+use crate::network::{describe_socket_options, leave_multicast_group, MulticastInterface, SocketOptions};
+use crate::transport::{Endpoint, FrameListener, FrameSender, Scheme};
+use crate::types::MESSAGE_TOTAL_SIZE;
+
+// 一次 poll 最多处理的事件数
+const EVENTS_CAPACITY: usize = 64;
+
+// 收到的一帧，带上它所属的组 (feed) 下标
+#[derive(Debug, Clone)]
+pub struct ReceivedFrame {
+    pub group: usize,                    // 在 GroupListener::endpoints() 中的下标
+    pub src: SocketAddr,                 // 发送方地址
+    pub len: usize,                      // 实际接收长度
+    pub data: [u8; MESSAGE_TOTAL_SIZE],
+    pub received_at: Instant,            // 从 Socket 读出的时间
+}
+
+impl ReceivedFrame {
+    pub fn bytes(&self) -> &[u8] {
+        &self.data[..self.len]
+    }
+}
+
+// 解码后的消息，保留所属组和来源，供后续处理 (重排、打印、统计)
+#[derive(Debug, Clone)]
+pub struct TaggedMessage {
+    pub group: usize,
+    pub src: SocketAddr,
+    pub message: BroadcastMessage,
+}
+
+enum FeedIo {
+    Udp(UdpSocket),
+    // TCP 是字节流，需要缓存不完整的帧
+    Tcp { stream: TcpStream, peer: SocketAddr, pending: Vec<u8> },
+}
+
+struct Feed {
+    endpoint: Endpoint,
+    io: FeedIo,
+    joined: bool, // 是否加入了组播组，关闭时需要离开
+}
+
+// 同时订阅多个组/端口的监听器，使用 mio (epoll) 统一轮询
+pub struct GroupListener {
+    poll: Poll,
+    events: Events,
+    feeds: Vec<Feed>,
+    interface: MulticastInterface,
+    source: Option<Ipv4Addr>,
+}
+
+impl GroupListener {
+    pub fn open(endpoints: &[Endpoint], interface: &MulticastInterface, source: Option<Ipv4Addr>, options: &SocketOptions) -> Result<Self, String> {
+        if endpoints.is_empty() {
+            return Err("At least one result address is required".to_string());
+        }
+
+        let poll = Poll::new().map_err(|e| format!("Failed to create poller: {}", e))?;
+        let mut feeds = Vec::with_capacity(endpoints.len());
+
+        for (index, endpoint) in endpoints.iter().enumerate() {
+            let io = match FrameListener::open(endpoint, interface, source, options)? {
+                FrameListener::Udp(socket) => {
+                    socket.set_nonblocking(true)
+                        .map_err(|e| format!("Failed to set non-blocking on {}: {}", endpoint, e))?;
+                    FeedIo::Udp(UdpSocket::from_std(socket))
+                }
+                FrameListener::Tcp(stream) => {
+                    let peer = stream.peer_addr()
+                        .map_err(|e| format!("Failed to read peer address of {}: {}", endpoint, e))?;
+                    stream.set_nonblocking(true)
+                        .map_err(|e| format!("Failed to set non-blocking on {}: {}", endpoint, e))?;
+                    FeedIo::Tcp { stream: TcpStream::from_std(stream), peer, pending: Vec::new() }
+                }
+            };
+
+            let mut feed = Feed { endpoint: endpoint.clone(), io, joined: endpoint.scheme == Scheme::Multicast };
+            let registered = match &mut feed.io {
+                FeedIo::Udp(socket) => poll.registry().register(socket, Token(index), Interest::READABLE),
+                FeedIo::Tcp { stream, .. } => poll.registry().register(stream, Token(index), Interest::READABLE),
+            };
+            registered.map_err(|e| format!("Failed to register {} with poller: {}", endpoint, e))?;
+            feeds.push(feed);
+        }
+
+        Ok(GroupListener {
+            poll,
+            events: Events::with_capacity(EVENTS_CAPACITY),
+            feeds,
+            interface: interface.clone(),
+            source,
+        })
+    }
+
+    // 把发送端也注册为一个 feed，用于接收对请求的回复 (例如重传的帧)
+    // 克隆的 Socket 与发送端共享非阻塞标志，FrameSender::send_frame 会处理 WouldBlock 并保持帧完整
+    pub fn add_reply_channel(&mut self, endpoint: &Endpoint, sender: &FrameSender) -> Result<usize, String> {
+        let index = self.feeds.len();
+        let mut io = match sender {
+            FrameSender::Udp { socket, .. } => {
+                let socket = socket.try_clone()
+                    .and_then(|socket| socket.set_nonblocking(true).map(|_| socket))
+                    .map_err(|e| format!("Failed to prepare reply channel {}: {}", endpoint, e))?;
+                FeedIo::Udp(UdpSocket::from_std(socket))
+            }
+            FrameSender::Tcp(stream) => {
+                let stream = stream.try_clone()
+                    .and_then(|stream| stream.set_nonblocking(true).map(|_| stream))
+                    .map_err(|e| format!("Failed to prepare reply channel {}: {}", endpoint, e))?;
+                FeedIo::Tcp { stream: TcpStream::from_std(stream), peer: endpoint.addr, pending: Vec::new() }
+            }
+        };
+
+        let registered = match &mut io {
+            FeedIo::Udp(socket) => self.poll.registry().register(socket, Token(index), Interest::READABLE),
+            FeedIo::Tcp { stream, .. } => self.poll.registry().register(stream, Token(index), Interest::READABLE),
+        };
+        registered.map_err(|e| format!("Failed to register {} with poller: {}", endpoint, e))?;
+
+        self.feeds.push(Feed { endpoint: endpoint.clone(), io, joined: false });
+        Ok(index)
+    }
+
+    pub fn endpoints(&self) -> impl Iterator<Item = &Endpoint> {
+        self.feeds.iter().map(|feed| &feed.endpoint)
+    }
+
+    pub fn endpoint(&self, group: usize) -> &Endpoint {
+        &self.feeds[group].endpoint
+    }
+
+    // 每个 feed 的 Socket 选项，用于启动时打印
+    pub fn describe(&self) -> Vec<String> {
+        self.feeds.iter()
+            .map(|feed| match &feed.io {
+                FeedIo::Udp(socket) => format!("{}: {}", feed.endpoint, describe_socket_options(socket)),
+                FeedIo::Tcp { stream, .. } => format!("{}: {}", feed.endpoint, describe_socket_options(stream)),
+            })
+            .collect()
+    }
+
+    // 等待任一 feed 可读 (timeout 为 None 时一直阻塞)，把收到的完整帧追加到 out
+    pub fn poll_frames(&mut self, timeout: Option<Duration>, out: &mut Vec<ReceivedFrame>) -> io::Result<()> {
+        self.poll.poll(&mut self.events, timeout)?;
+
+        for event in self.events.iter() {
+            let group = event.token().0;
+            let feed = &mut self.feeds[group];
+
+            match &mut feed.io {
+                FeedIo::Udp(socket) => {
+                    // 边沿触发：必须一直读到 WouldBlock
+                    let mut data = [0u8; MESSAGE_TOTAL_SIZE];
+                    loop {
+                        match socket.recv_from(&mut data) {
+                            Ok((len, src)) => out.push(ReceivedFrame { group, src, len, data, received_at: Instant::now() }),
+                            Err(e) if e.kind() == io::ErrorKind::WouldBlock => break,
+                            Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                            Err(e) => return Err(e),
+                        }
+                    }
+                }
+                FeedIo::Tcp { stream, peer, pending } => {
+                    let mut chunk = [0u8; MESSAGE_TOTAL_SIZE * 32];
+                    loop {
+                        match stream.read(&mut chunk) {
+                            Ok(0) => return Err(io::Error::new(io::ErrorKind::UnexpectedEof,
+                                format!("Connection to {} closed by peer", feed.endpoint))),
+                            Ok(n) => pending.extend_from_slice(&chunk[..n]),
+                            Err(e) if e.kind() == io::ErrorKind::WouldBlock => break,
+                            Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                            Err(e) => return Err(e),
+                        }
+                    }
+
+                    // 按固定帧长切分，剩余字节留到下次
+                    let received_at = Instant::now();
+                    let complete = pending.len() / MESSAGE_TOTAL_SIZE * MESSAGE_TOTAL_SIZE;
+                    for frame in pending[..complete].chunks_exact(MESSAGE_TOTAL_SIZE) {
+                        let mut data = [0u8; MESSAGE_TOTAL_SIZE];
+                        data.copy_from_slice(frame);
+                        out.push(ReceivedFrame { group, src: *peer, len: MESSAGE_TOTAL_SIZE, data, received_at });
+                    }
+                    pending.drain(..complete);
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    // 离开所有组播组 (drop 时也会调用)
+    pub fn leave_all(&mut self) {
+        for feed in self.feeds.iter_mut().filter(|feed| feed.joined) {
+            feed.joined = false;
+            if let (FeedIo::Udp(socket), IpAddr::V4(group)) = (&feed.io, feed.endpoint.addr.ip()) {
+                match leave_multicast_group(socket, group, &self.interface, self.source) {
+                    Ok(()) => println!("Left multicast group {}", feed.endpoint),
+                    Err(e) => eprintln!("Warning: {}", e),
+                }
+            }
+        }
+    }
+}
+
+impl Drop for GroupListener {
+    fn drop(&mut self) {
+        self.leave_all();
+    }
+}
@@ -1,176 +1,637 @@
 // src/main.rs
 
-use clap::Parser;
-use std::net::{ToSocketAddrs, UdpSocket};
+use std::io::Write;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
 
 // 导入其他模块
 mod types;
 mod encoding;
 mod network;
 mod params;
+mod transport;
+mod listener;
+mod summary;
+mod sequence;
+mod recovery;
+mod instance;
+mod feed;
+mod arbitration;
+mod filter;
+mod risk;
+mod gateway;
+mod position;
+mod journal;
+mod config;
+mod product;
+mod tif;
+mod stops;
+mod execution;
+mod market_maker;
+mod scenario;
+mod regression;
+mod clock;
+mod layout;
+mod hexdump;
 
-use types::{Order, get_nanos_since_epoch, MESSAGE_TOTAL_SIZE, MSG_ORDER_CANCEL};
-use encoding::{serialize_order, calculate_checksum,decode_broadcast_message};
-use network::{create_multicast_listener, send_message};
-use params::{Args, Command, SubmitArgs, CancelArgs};
+use types::{Order, get_nanos_since_epoch};
+use types::{ORDER_TYPE_BUY, ORDER_PRICE_TYPE_LIMIT, ORDER_PRICE_TYPE_STOP, ORDER_PRICE_TYPE_STOP_LIMIT, ORDER_FLAG_ICEBERG, ORDER_FLAG_POST_ONLY};
+use network::{resolve_interface, SocketOptions};
+use params::{Algo, Command, FrameDisplay, OrdersCommand, RawCommand, OutputFormat, SubmitArgs, CancelArgs, ExecuteArgs, MarketMakeArgs, TimeInForce, resolve_state_dir};
+use execution::{ExecutionPlan, ParentExecution};
+use market_maker::{MarketMaker, QuoteConfig};
+use transport::{parse_endpoint, FrameSender};
+use listener::GroupListener;
+use recovery::RecoveryBuffer;
+use instance::InstanceTracker;
+use feed::FeedHandler;
+use arbitration::Arbiter;
+use filter::MessageFilter;
+use risk::{RiskConfig, RiskEngine};
+use gateway::OrderGateway;
+use position::PositionBook;
+use journal::{read_journal, print_order_list, print_order, OrderJournal};
+use product::{default_products_path, ProductMaster};
+use clock::Clock;
+use tif::{format_local_time, parse_session_close, resolve_time_in_force};
+use types::format_instance_tag;
 
 
 const DEFAULT_TRADE_ADDR: &str = "239.0.0.1:5000";
 const DEFAULT_STATUS_ADDR: &str = "239.0.0.2:5001";
-// 监听组播时，绑定地址需要包含端口，但IP通常是0.0.0.0
-// 为了简化，我们只监听 trade_addr 或 status_addr 的端口
+// 组播接口默认为 0.0.0.0，即由系统按路由表选择网卡
+// 多网卡主机上应通过 --interface 显式指定
 const DEFAULT_LISTEN_IP: &str = "0.0.0.0";
+// 监听循环检查退出标志的间隔
+const SHUTDOWN_POLL_INTERVAL: Duration = Duration::from_millis(200);
+// DAY 订单默认在本地时间 17:00 过期
+const DEFAULT_SESSION_CLOSE: &str = "17:00";
 
 
 fn main() -> Result<(), String> {
-    let args = Args::parse();
-    let trade_addr = &args.trade_addr;
-    let result_addr = &args.result_addr;
-    
-    let socket = UdpSocket::bind("0.0.0.0:0")
-        .map_err(|e| format!("Failed to bind UDP socket: {}", e))?;
+    // 命令行 + 环境变量 + 配置文件 profile
+    let (args, profile) = config::parse_args()?;
+    let (profile_name, profile) = profile.unzip();
+    let profile = profile.unwrap_or_default();
+    let state_dir = resolve_state_dir(args.state_dir.clone());
+    let positions = PositionBook::load(state_dir.join("positions.toml"))?;
 
-    let listener_socket = create_multicast_listener(result_addr)?;
-    println!("📡 Starting Broadcast Listener on {}", result_addr);
-    
+    let journal_path = state_dir.join("orders.journal");
+
+    // 产品主数据：--products，其次 profile 中的 products_file，最后默认路径 (可不存在)
+    let products_path = args.product_file.clone()
+        .or(profile.products_file.clone())
+        .or_else(default_products_path);
+    let products = match &products_path {
+        Some(path) => ProductMaster::load(path)?,
+        None => ProductMaster::default(),
+    };
 
-    // 2. 尝试解析组播地址并设置 TTL
-    if let Ok(mut addrs) = trade_addr.to_socket_addrs() {
-        if let Some(socket_addr) = addrs.next() {
-            if socket_addr.ip().is_multicast() {
-                // Multicast TTL (Time To Live) 默认为 1，我们设置为 1 以限制在本地网络。
-                // 如果需要跨路由器，应设置为更高值。
-                if let Err(e) = socket.set_multicast_ttl_v4(10) {
-                    eprintln!("Warning: Failed to set Multicast TTL (this is often okay for simple sending): {}", e);
+    // 查看持仓和订单日志不需要网络
+    match &args.command {
+        Command::Positions => {
+            positions.print();
+            return Ok(());
+        }
+        Command::Orders { command } => {
+            let entries = read_journal(&journal_path)?;
+            let now = get_nanos_since_epoch()?;
+            return match command {
+                OrdersCommand::List => {
+                    print_order_list(&entries, now);
+                    Ok(())
                 }
-            }
+                OrdersCommand::Show { order_id } => print_order(&entries, *order_id, now, args.frames),
+            };
         }
+        Command::Raw { command: RawCommand::Decode { hex } } => {
+            layout::print_annotated(&layout::parse_frame_hex(hex)?);
+            return Ok(());
+        }
+        _ => {}
+    }
+
+    let running = install_shutdown_handler()?;
+    let trade_addr = parse_endpoint(&args.trade_addr)?;
+    // 指定了 A/B 线路时只订阅这两条线路 (下标 0 和 1)
+    let result_addrs = match (&args.line_a, &args.line_b) {
+        (Some(line_a), Some(line_b)) => vec![parse_endpoint(line_a)?, parse_endpoint(line_b)?],
+        _ => args.result_addr.iter()
+            .map(|addr| parse_endpoint(addr))
+            .collect::<Result<Vec<_>, _>>()?,
+    };
+    let interface = resolve_interface(&args.interface)?;
+
+    // 1. Socket 选项：TTL、回环、缓冲区、SO_REUSEPORT 和 DSCP/TOS
+    let socket_options = SocketOptions {
+        multicast_ttl: args.ttl,
+        multicast_loop: args.multicast_loop,
+        recv_buffer_size: args.rcvbuf,
+        send_buffer_size: args.sndbuf,
+        reuse_port: args.reuse_port,
+        tos: args.tos.map(u32::from).or(args.dscp.map(|dscp| u32::from(dscp) << 2)),
+    };
+
+    // 2. 按传输方式 (mcast/udp/tcp) 创建发送端和监听端
+    let sender = FrameSender::open(&trade_addr, &interface, &socket_options)?;
+
+    let mut listener = GroupListener::open(&result_addrs, &interface, args.source, &socket_options)?;
+    for endpoint in listener.endpoints() {
+        println!("📡 Starting Broadcast Listener on {}", endpoint);
+    }
+    if let Some(source) = args.source {
+        println!("Source-Specific Join: {}", source);
+    }
+    if !args.allow_source.is_empty() {
+        println!("Allowed Sources: {:?}", args.allow_source);
     }
     
+    if let Some(name) = &profile_name {
+        println!("Profile: {}", name);
+    }
+    if let Some(path) = &products_path {
+        println!("Products: {}", path.display());
+    }
     println!("Target Trade Address: {}", trade_addr);
-    println!("Result  Address: {}", result_addr);
+    println!("Interface: {}", interface);
+
+    // 下单风控 (可选)，状态保存在本地状态目录
+    let state_path = state_dir.join("risk_state.toml");
+    let risk = match (&args.risk_config, profile.risk) {
+        (Some(path), _) => {
+            println!("Risk Config: {} (state: {})", path.display(), state_path.display());
+            Some(RiskEngine::load(RiskConfig::load(path)?, state_path)?)
+        }
+        (None, Some(risk_config)) => {
+            println!("Risk Config: profile (state: {})", state_path.display());
+            Some(RiskEngine::load(risk_config, state_path)?)
+        }
+        (None, None) => None,
+    };
+    if args.clock != Clock::System {
+        println!("Clock: {}", args.clock);
+    }
+    let mut gateway = OrderGateway::new(sender, trade_addr, risk, positions, OrderJournal::open(journal_path)?, args.clock);
+    println!("Sender   Socket: {}", gateway.describe());
+    for description in listener.describe() {
+        println!("Listener Socket: {}", description);
+    }
 
-    // 2. 根据子命令执行逻辑
+    // 可选：重传请求通道，回复通过同一个 Socket 接收
+    let recovery = match &args.retransmit_addr {
+        Some(addr) => {
+            let endpoint = parse_endpoint(addr)?;
+            let channel = FrameSender::open(&endpoint, &interface, &socket_options)?;
+            listener.add_reply_channel(&endpoint, &channel)?;
+            println!("Retransmit Address: {} (timeout {}ms)", endpoint, args.recovery_timeout_ms);
+            Some(RecoveryBuffer::new(channel, endpoint, Duration::from_millis(args.recovery_timeout_ms)))
+        }
+        None => None,
+    };
+
+    if let Some(primary) = &args.primary_instance {
+        println!("Primary Instance: {}", format_instance_tag(primary));
+    }
+    let arbiter = if args.line_a.is_some() {
+        println!("A/B Arbitration: Line A={} | Line B={}", listener.endpoint(0), listener.endpoint(1));
+        Some(Arbiter::new(0, 1, Duration::from_millis(args.arbitration_window_ms)))
+    } else {
+        None
+    };
+    let mut feed = FeedHandler::new(args.allow_source.clone(), arbiter, recovery, InstanceTracker::new(args.primary_instance), args.frames);
+
+    // 3. 根据子命令执行逻辑
+    let mut execution = None;
+    let mut maker = None;
+    let mut scenario = None;
+    let mut suite = None;
     match args.command {
         Command::Submit(submit_args) => {
-            handle_submit(submit_args, &socket, trade_addr)?;
+            let session_close = match (submit_args.session_close, &profile.session_close) {
+                (Some(close), _) => close,
+                (None, Some(close)) => parse_session_close(close)?,
+                (None, None) => parse_session_close(DEFAULT_SESSION_CLOSE)?,
+            };
+            handle_submit(submit_args, profile.default_product, session_close, &products, &mut gateway, args.frames)?;
         }
         Command::Cancel(cancel_args) => {
-            handle_cancel(cancel_args, &socket, trade_addr)?;
+            handle_cancel(cancel_args, &mut gateway, args.frames)?;
+        }
+        Command::Raw { command: RawCommand::Send { hex, fix_checksum } } => {
+            handle_raw_send(&hex, fix_checksum, &mut gateway)?;
         }
+        Command::Execute(execute_args) => {
+            execution = Some(plan_execution(execute_args, profile.default_product, &products, &gateway)?);
+        }
+        Command::MarketMake(market_make_args) => {
+            maker = Some(plan_market_maker(market_make_args, profile.default_product, &products)?);
+        }
+        Command::RunScenario(scenario_args) => {
+            scenario = Some(scenario_args.script);
+        }
+        Command::TestEngine(test_args) => {
+            suite = Some((regression::Suite::load(&test_args.scenario)?, test_args.junit));
+        }
+        Command::Listen | Command::Positions | Command::Orders { .. } | Command::Raw { .. } => {}
     }
 
-    receive_broadcasts(listener_socket)
-        .map_err(|e| format!("Broadcast receiver failed: {}", e))?;
+    let filter = MessageFilter {
+        products: args.filter.products,
+        msg_type: args.filter.msg_type,
+        order_id: args.filter.order_id,
+        min_quantity: args.filter.min_qty,
+        price_range: args.filter.price_range,
+        expression: args.filter.filter,
+    };
+    // execute、run-scenario 和 test-engine 运行到结束；market-make 和其它子命令持续运行直到 Ctrl+C
+    let result = if let Some(script) = &scenario {
+        let (session, result) = scenario::run(script, scenario::Session { listener, feed, gateway }, running.clone());
+        scenario::Session { listener, feed, gateway } = session;
+        result
+    } else if let Some((suite, junit_path)) = &suite {
+        regression::run(suite, junit_path, &mut listener, &mut feed, &mut gateway, &running)
+    } else {
+        match (execution.as_mut(), maker.as_mut()) {
+            (Some(execution), _) => execution::run(execution, &mut listener, &mut feed, &mut gateway, &running)
+                .map_err(|e| format!("Execution failed: {}", e)),
+            (None, Some(maker)) => market_maker::run(maker, &mut listener, &mut feed, &mut gateway, &running)
+                .map_err(|e| format!("Market maker failed: {}", e)),
+            (None, None) => receive_broadcasts(&mut listener, &mut feed, &mut gateway, &filter, &products, args.output_format, &running)
+                .map_err(|e| format!("Broadcast receiver failed: {}", e)),
+        }
+    };
+
+    // 退出：离开组播组，保存状态，刷新输出，打印会话统计
+    listener.leave_all();
+    if gateway.pending_stops() > 0 {
+        println!("⚠️  {} synthetic stop(s) not triggered, discarded", gateway.pending_stops());
+    }
+    if let Err(e) = gateway.close() {
+        eprintln!("Warning: {}", e);
+    }
+    let _ = std::io::stdout().flush();
+    feed.print_summary();
+    gateway.print_positions();
+    if let Some(execution) = &execution {
+        execution.print_report(&products);
+    }
+    if let Some(maker) = &maker {
+        maker.print_report(&products);
+    }
+    result?;
 
 
     Ok(())
 }
 
-fn handle_submit(args: SubmitArgs, socket: &UdpSocket, trade_addr: &str) -> Result<(), String> {
-    // 1. 时间戳和订单 ID 计算
-    let submit_time = get_nanos_since_epoch()?;
-    let expire_time = if args.expire > 0 {
-        let expire_nanos: u64 = args.expire.checked_mul(1_000_000_000)
-            .ok_or_else(|| "Expiration duration overflow".to_string())?;
-        
-        submit_time.checked_add(expire_nanos)
-            .ok_or_else(|| "Expiration time overflow".to_string())?
-    } else {
-        0 // 0 means GTC
+fn handle_submit(args: SubmitArgs, default_product: Option<u16>, session_close: chrono::NaiveTime, products: &ProductMaster, gateway: &mut OrderGateway, frames: FrameDisplay) -> Result<(), String> {
+    // 0. 产品和价格：有主数据时按显示单位换算并检查最小变动价位和整手
+    let product_id = products.resolve(args.symbol.as_deref(), args.product_id.or(default_product))?;
+    let product = products.by_id(product_id);
+    let parse_price = |text: &str| products.parse_price(product_id, text);
+
+    let base_price_type = args.price_type;
+    let is_stop = matches!(base_price_type, ORDER_PRICE_TYPE_STOP | ORDER_PRICE_TYPE_STOP_LIMIT);
+    let needs_price = matches!(base_price_type, ORDER_PRICE_TYPE_LIMIT | ORDER_PRICE_TYPE_STOP_LIMIT);
+    let price = match &args.price {
+        Some(text) => parse_price(text)?,
+        None if needs_price => return Err("--price is required for limit and stop-limit orders".to_string()),
+        None => 0,
+    };
+    let trigger_price = match (&args.trigger_price, is_stop) {
+        (Some(text), true) => parse_price(text)?,
+        (None, true) => return Err("--trigger-price is required for stop and stop-limit orders".to_string()),
+        (Some(_), false) => return Err("--trigger-price only applies to stop and stop-limit orders".to_string()),
+        (None, false) => 0,
     };
+    if args.synthetic_stop && !is_stop {
+        return Err("--synthetic-stop only applies to stop and stop-limit orders".to_string());
+    }
 
-    // 订单 ID 简单使用 submit_time
-    let order_id = submit_time; 
+    // 冰山和 post-only 只用于限价单；冰山与止损共用扩展字节，不能同时使用
+    let mut order_flags = 0;
+    if let Some(display_quantity) = args.display_qty {
+        if base_price_type != ORDER_PRICE_TYPE_LIMIT {
+            return Err("--display-qty (iceberg) only applies to limit orders".to_string());
+        }
+        if display_quantity == 0 || display_quantity >= args.quantity {
+            return Err(format!("--display-qty must be between 1 and quantity - 1 ({})", args.quantity.saturating_sub(1)));
+        }
+        order_flags |= ORDER_FLAG_ICEBERG;
+    }
+    if args.post_only {
+        if base_price_type != ORDER_PRICE_TYPE_LIMIT || matches!(args.tif, TimeInForce::Ioc | TimeInForce::Fok) {
+            return Err("--post-only only applies to resting limit orders (not market, stop, IOC or FOK)".to_string());
+        }
+        order_flags |= ORDER_FLAG_POST_ONLY;
+    }
+
+    if let Some(info) = product {
+        if needs_price {
+            info.validate(price, args.quantity)?;
+        }
+        if is_stop {
+            info.validate(trigger_price, args.quantity)?;
+        }
+        if let Some(display_quantity) = args.display_qty {
+            info.validate(price, display_quantity)?;
+        }
+    }
+
+    // 1. 时间戳和订单 ID 计算 (--submit-time/--order-id 覆盖时钟，便于复现)
+    let submit_time = match args.submit_time {
+        Some(submit_time) => submit_time,
+        None => gateway.now()?,
+    };
+    let (expire_time, tif_flags) = resolve_time_in_force(args.tif, args.expire_at, args.expire, session_close, submit_time)?;
+
+    // 订单 ID 默认使用 submit_time
+    let order_id = args.order_id.unwrap_or(submit_time);
 
     // 2. 构建 Order 结构体
     let order = Order {
-        product_id: args.product_id,
-        order_id: order_id,
-        price: args.price,
+        product_id,
+        order_id,
+        price,
         quantity: args.quantity,
         order_type: args.order_type,
-        price_type: args.price_type,
+        price_type: base_price_type | tif_flags | order_flags,
         submit_time,
         expire_time,
+        trigger_price,
+        display_quantity: args.display_qty.unwrap_or(0),
     };
 
-    // 3. 序列化消息
-    let serialized_message = serialize_order(&order);
-
-    // 4. 发送消息
-    send_message(socket, trade_addr, &serialized_message)?;
+    // 3. 风控检查、序列化并发送 (合成止损单先挂在本地)
+    let serialized_message = if args.synthetic_stop {
+        gateway.hold_stop(order.clone())?;
+        None
+    } else {
+        Some(gateway.submit(&order)?)
+    };
 
-    // 5. 打印结果
-    println!("--- Order Submit Request (Sent to {}) ---", trade_addr);
+    // 4. 打印结果
+    match serialized_message {
+        Some(_) => println!("--- Order Submit Request (Sent to {}) ---", gateway.endpoint()),
+        None => println!("--- Synthetic Stop Held Locally (sent to {} when triggered) ---", gateway.endpoint()),
+    }
     println!("Order ID: {}", order_id);
-    println!("Product ID: {}", order.product_id);
-    println!("Price: {}, Quantity: {}", order.price, order.quantity);
-    println!("Serialized Message ({} bytes): {:?}", MESSAGE_TOTAL_SIZE, serialized_message);
+    println!("Type: {}", order.price_type_name());
+    match product {
+        Some(info) => {
+            println!("Product ID: {} ({})", order.product_id, info.symbol);
+            println!("Price: {} (wire {}), Quantity: {}", info.format_price(order.price), order.price, order.quantity);
+            if order.is_stop() {
+                println!("Trigger Price: {} (wire {})", info.format_price(order.trigger_price), order.trigger_price);
+            }
+        }
+        None => {
+            println!("Product ID: {}", order.product_id);
+            println!("Price: {}, Quantity: {}", order.price, order.quantity);
+            if order.is_stop() {
+                println!("Trigger Price: {}", order.trigger_price);
+            }
+        }
+    }
+    if order.is_iceberg() {
+        println!("Display Quantity: {}", order.display_quantity);
+    }
+    if order.is_post_only() {
+        println!("Post Only: true");
+    }
+    match order.expire_time {
+        0 => println!("Time In Force: {}", args.tif.name()),
+        // --expire 秒数的旧用法按 GTD 显示
+        expire_time => println!("Time In Force: {} (expires {})",
+            if args.tif == TimeInForce::Gtc { "GTD" } else { args.tif.name() }, format_local_time(expire_time)),
+    }
+    if let Some(serialized_message) = serialized_message {
+        hexdump::print_frame("Serialized Message", &serialized_message, frames);
+    }
     
     Ok(())
 }
 
-fn handle_cancel(args: CancelArgs, socket: &UdpSocket, trade_addr: &str) -> Result<(), String> {
-    // 1. 构建撤单消息
-    let mut cancel_buf = [0u8; MESSAGE_TOTAL_SIZE];
-    cancel_buf[1] = MSG_ORDER_CANCEL; // 消息类型
+fn plan_execution(args: ExecuteArgs, default_product: Option<u16>, products: &ProductMaster, gateway: &OrderGateway) -> Result<ParentExecution, String> {
+    let product_id = products.resolve(args.symbol.as_deref(), args.product_id.or(default_product))?;
+    if args.quantity == 0 || args.duration == 0 || args.slices == 0 {
+        return Err("--quantity, --duration and --slices must be greater than 0".to_string());
+    }
+    if !(args.participation > 0.0 && args.participation <= 100.0) {
+        return Err(format!("--participation must be in (0, 100]: {}", args.participation));
+    }
+    let limit_price = args.limit_price.as_deref()
+        .map(|text| products.parse_price(product_id, text))
+        .transpose()?;
 
-    // Order ID (假设从第 2 个字节开始)
-    cancel_buf[2..10].copy_from_slice(&args.order_id.to_be_bytes());
-    
-    // 2. 计算 Checksum 并放置
-    cancel_buf[0] = calculate_checksum(&cancel_buf);
+    let plan = ExecutionPlan {
+        product_id,
+        side: args.order_type,
+        quantity: args.quantity,
+        algo: args.algo,
+        duration: Duration::from_secs(args.duration),
+        slices: args.slices,
+        participation: args.participation / 100.0,
+        limit_price,
+        lot_size: products.by_id(product_id).map(|info| info.lot_size).unwrap_or(1),
+    };
+    println!("--- Execution ({}) ---", plan.algo.name());
+    println!("Product ID: {} | Side: {} | Quantity: {} | Duration: {}s",
+        product_id, if plan.side == ORDER_TYPE_BUY { "BUY" } else { "SELL" }, plan.quantity, args.duration);
+    match plan.algo {
+        Algo::Twap => println!("Slices: {}", plan.slices),
+        Algo::Vwap => println!("Participation: {}%", args.participation),
+    }
 
-    // 3. 发送消息
-    send_message(socket, trade_addr, &cancel_buf)?;
+    // 到达价格：启动时已知的最近成交价，没有则取执行期间的第一笔成交
+    Ok(ParentExecution::new(plan, gateway.last_trade_price(product_id)))
+}
 
-    // 4. 打印结果
-    println!("--- Order Cancel Request (Sent to {}) ---", trade_addr);
+fn plan_market_maker(args: MarketMakeArgs, default_product: Option<u16>, products: &ProductMaster) -> Result<MarketMaker, String> {
+    let product_id = products.resolve(args.symbol.as_deref(), args.product_id.or(default_product))?;
+    if args.levels == 0 || args.quantity == 0 || args.max_position == 0 {
+        return Err("--levels, --quantity and --max-position must be greater than 0".to_string());
+    }
+    let parse_price = |text: &str| products.parse_price(product_id, text);
+    let (mid, spread, step) = (parse_price(&args.mid)?, parse_price(&args.spread)?, parse_price(&args.step)?);
+    if args.levels > 1 && step == 0 {
+        return Err("--step must be greater than 0 when quoting more than one level".to_string());
+    }
+    // 最远一档与中间价的距离；后面生成阶梯时不再检查溢出
+    let widest = step.checked_mul(u64::from(args.levels - 1))
+        .and_then(|offset| offset.checked_add(spread))
+        .ok_or("Ladder width overflows: reduce --spread, --step or --levels")?;
+    if widest >= mid {
+        return Err("Bid ladder would reach zero: reduce --spread, --step or --levels".to_string());
+    }
+    if mid.checked_add(widest).is_none() {
+        return Err("Ask ladder overflows: reduce --mid, --spread, --step or --levels".to_string());
+    }
+
+    // 中间价、价差和档差都是最小变动的整数倍时，所有档位价格都合法
+    let product = products.by_id(product_id);
+    if let Some(info) = product {
+        for price in [mid, spread, step] {
+            info.validate(price, args.quantity)?;
+        }
+    }
+
+    let config = QuoteConfig {
+        product_id,
+        mid,
+        spread,
+        step,
+        levels: args.levels,
+        quantity: args.quantity,
+        max_position: args.max_position,
+        lot_size: product.map(|info| info.lot_size).unwrap_or(1),
+    };
+    println!("--- Market Maker ---");
+    println!("Product ID: {} | Mid: {} | Spread: {} | Step: {}", product_id,
+        products.format_price(product_id, mid), products.format_price(product_id, spread), products.format_price(product_id, step));
+    println!("Levels: {} x {} per side | Max Position: {}", config.levels, config.quantity, config.max_position);
+    Ok(MarketMaker::new(config))
+}
+
+fn handle_cancel(args: CancelArgs, gateway: &mut OrderGateway, frames: FrameDisplay) -> Result<(), String> {
+    // 1. 构建并发送撤单消息
+    let cancel_buf = gateway.cancel(args.order_id)?;
+
+    // 2. 打印结果
+    println!("--- Order Cancel Request (Sent to {}) ---", gateway.endpoint());
     println!("Order ID to Cancel: {}", args.order_id);
-    println!("Serialized Message ({} bytes): {:?}", MESSAGE_TOTAL_SIZE, cancel_buf);
+    hexdump::print_frame("Serialized Message", &cancel_buf, frames);
     
     Ok(())
 }
 
 
+fn handle_raw_send(hex: &str, fix_checksum: bool, gateway: &mut OrderGateway) -> Result<(), String> {
+    // 1. 校验长度，按需修正校验和
+    let mut frame = layout::parse_frame_hex(hex)?;
+    let expected = encoding::calculate_checksum(&frame);
+    if frame[0] != expected {
+        if fix_checksum {
+            println!("Checksum fixed: 0x{:02x} -> 0x{:02x}", frame[0], expected);
+            frame[0] = expected;
+        } else {
+            println!("⚠️  Checksum mismatch (0x{:02x}, expected 0x{:02x}), sending as-is", frame[0], expected);
+        }
+    }
+
+    // 2. 发送并打印逐字段解码
+    gateway.send_raw(&frame)?;
+    println!("--- Raw Frame (Sent to {}) ---", gateway.endpoint());
+    layout::print_annotated(&frame);
+
+    Ok(())
+}
+
+
+// 安装 Ctrl+C (SIGINT/SIGTERM) 处理：只置位退出标志，由监听循环负责清理
+fn install_shutdown_handler() -> Result<Arc<AtomicBool>, String> {
+    let running = Arc::new(AtomicBool::new(true));
+    let flag = running.clone();
+    ctrlc::set_handler(move || flag.store(false, Ordering::SeqCst))
+        .map_err(|e| format!("Failed to install Ctrl+C handler: {}", e))?;
+    Ok(running)
+}
 
-fn receive_broadcasts(listener_socket:UdpSocket) -> Result<(), String> {
+fn receive_broadcasts(listener: &mut GroupListener, feed: &mut FeedHandler, gateway: &mut OrderGateway, filter: &MessageFilter, products: &ProductMaster, output_format: OutputFormat, running: &AtomicBool) -> Result<(), String> {
     println!("\n=============================================");
     
+    if !filter.is_empty() {
+        println!("Filter: {}", filter);
+    }
     println!("Ctrl+C to stop...");
     println!("=============================================");
 
     
     
-    // 缓冲区大小固定为 MESSAGE_TOTAL_SIZE
-    let mut buf = [0u8; MESSAGE_TOTAL_SIZE]; 
-
-    loop {
-        match listener_socket.recv_from(&mut buf) {
-            Ok((len, src)) => {
-                // 仅为了演示，我们跳过校验和检查。实际应用中应在此处验证 buf[0]
-                let checksum_ok = calculate_checksum(&buf) == buf[0]; 
-                if checksum_ok {
-                    //println!("check sum is fine");
-                }
-                // 假设校验和通过，进行解码
-                match decode_broadcast_message(&buf[..len]) {
-                    Ok(decoded_msg) => {
-                        println!("[{}] {}", src, decoded_msg);
-                    },
-                    Err(e) => {
-                        eprintln!("[{}] Error decoding message: {}", src, e);
+    // 按序交付给下游的消息
+    let mut delivered = Vec::new();
+    let mut fills = Vec::new();
+    let mut triggered = Vec::new();
+
+    while running.load(Ordering::SeqCst) {
+        feed.poll(listener, SHUTDOWN_POLL_INTERVAL, &mut delivered)?;
+
+        for tagged in delivered.drain(..) {
+            gateway.observe(&tagged.message, &mut fills)?;
+            gateway.trigger_stops(&tagged.message, &mut triggered)?;
+            if filter.matches(&tagged.message) {
+                match output_format {
+                    OutputFormat::Text => println!("[{}] [{}] {}", listener.endpoint(tagged.group), tagged.src, products.render(&tagged.message)),
+                    OutputFormat::Json => {
+                        let mut value = tagged.message.to_json();
+                        products.annotate_json(&tagged.message, &mut value);
+                        value["group"] = listener.endpoint(tagged.group).to_string().into();
+                        value["src"] = tagged.src.to_string().into();
+                        println!("{}", value);
                     }
                 }
             }
-            Err(e) => {
-                // 忽略非致命错误，例如 EWOULDBLOCK 或 EAGAIN
-                if e.kind() == std::io::ErrorKind::Interrupted {
-                    continue;
+            for fill in fills.drain(..) {
+                match output_format {
+                    OutputFormat::Text => println!("{}", fill),
+                    OutputFormat::Json => println!("{}", fill.to_json()),
                 }
-                return Err(format!("Socket receive error: {}", e));
             }
+            // 本地止损被触发：按市价/限价发出，风控拒绝时只报告不退出
+            for order in triggered.drain(..) {
+                println!("⏰ STOP TRIGGERED: OrderID={} | Product={} | Sending {} Price={} Qty={}",
+                    order.order_id, order.product_id, order.price_type_name(), order.price, order.quantity);
+                if let Err(e) = gateway.submit(&order) {
+                    eprintln!("Failed to send triggered stop {}: {}", order.order_id, e);
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn market_make(mid: u64, spread: u64, step: u64, levels: u32) -> MarketMakeArgs {
+        MarketMakeArgs {
+            product_id: Some(1),
+            symbol: None,
+            mid: mid.to_string(),
+            spread: spread.to_string(),
+            step: step.to_string(),
+            levels,
+            quantity: 10,
+            max_position: 100,
         }
     }
-}
\ No newline at end of file
+
+    fn plan_error(args: MarketMakeArgs) -> Option<String> {
+        plan_market_maker(args, None, &ProductMaster::default()).err()
+    }
+
+    #[test]
+    fn market_maker_rejects_overflowing_ladders() {
+        // (levels - 1) * step 溢出
+        let error = plan_error(market_make(1000, 1, u64::MAX / 2, 4));
+        assert_eq!(error.as_deref(), Some("Ladder width overflows: reduce --spread, --step or --levels"));
+        // step * (levels - 1) 不溢出，但加上 spread 后溢出
+        let error = plan_error(market_make(1000, u64::MAX, 1, 2));
+        assert_eq!(error.as_deref(), Some("Ladder width overflows: reduce --spread, --step or --levels"));
+        // 宽度本身合法，但 mid + 宽度溢出
+        let error = plan_error(market_make(u64::MAX - 5, 3, 2, 3));
+        assert_eq!(error.as_deref(), Some("Ask ladder overflows: reduce --mid, --spread, --step or --levels"));
+        // 宽度达到中间价
+        let error = plan_error(market_make(100, 50, 25, 3));
+        assert_eq!(error.as_deref(), Some("Bid ladder would reach zero: reduce --spread, --step or --levels"));
+    }
+
+    #[test]
+    fn market_maker_accepts_widest_valid_ladder() {
+        assert_eq!(plan_error(market_make(101, 50, 25, 3)), None);
+        assert_eq!(plan_error(market_make(u64::MAX - 7, 3, 2, 3)), None);
+    }
+}
@@ -0,0 +1,215 @@
+// src/market_maker.rs
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+
+use crate::feed::FeedHandler;
+use crate::gateway::OrderGateway;
+use crate::listener::GroupListener;
+use crate::position::Fill;
+use crate::product::ProductMaster;
+use crate::types::{Order, ORDER_PRICE_TYPE_LIMIT, ORDER_TYPE_BUY, ORDER_TYPE_SELL};
+
+// 做市循环检查退出标志的间隔
+const QUOTE_POLL_INTERVAL: Duration = Duration::from_millis(200);
+
+// 报价参数 (价格均为线上单位)
+#[derive(Debug, Clone)]
+pub struct QuoteConfig {
+    pub product_id: u16,
+    pub mid: u64,
+    pub spread: u64, // 第一档与中间价的距离
+    pub step: u64,   // 相邻档位的价差
+    pub levels: u32,
+    pub quantity: u32,
+    pub max_position: u32,
+    pub lot_size: u32,
+}
+
+// 挂在引擎上的一张报价单
+#[derive(Debug)]
+struct Quote {
+    order_id: u64,
+    quantity: u32,
+    filled: u32,
+}
+
+// 做市状态：当前报价和统计计数
+pub struct MarketMaker {
+    config: QuoteConfig,
+    quotes: Vec<Quote>,
+    last_order_id: u64,
+    quoted: u64,
+    cancelled: u64,
+    requotes: u64,
+    fills: u64,
+    bought: u64,
+    sold: u64,
+}
+
+impl MarketMaker {
+    pub fn new(config: QuoteConfig) -> Self {
+        MarketMaker {
+            config,
+            quotes: Vec::new(),
+            last_order_id: 0,
+            quoted: 0,
+            cancelled: 0,
+            requotes: 0,
+            fills: 0,
+            bought: 0,
+            sold: 0,
+        }
+    }
+
+    // 订单 ID 使用时钟的当前纳秒时间，一次挂多档时保证严格递增
+    fn next_order_id(&mut self, gateway: &mut OrderGateway) -> Result<u64, String> {
+        self.last_order_id = gateway.now()?.max(self.last_order_id + 1);
+        Ok(self.last_order_id)
+    }
+
+    // 按当前持仓计算报价阶梯 (方向, 价格, 数量)
+    //   买单全部成交后持仓不超过 +max_position，卖单全部成交后不低于 -max_position
+    fn ladder(&self, position: i64) -> Vec<(u8, u64, u32)> {
+        let config = &self.config;
+        let limit = i64::from(config.max_position);
+        let lot = config.lot_size.max(1);
+        let mut ladder = Vec::new();
+
+        for (side, mut capacity) in [(ORDER_TYPE_BUY, limit - position), (ORDER_TYPE_SELL, limit + position)] {
+            for level in 0..u64::from(config.levels) {
+                let offset = config.spread + level * config.step;
+                let price = if side == ORDER_TYPE_BUY {
+                    config.mid.checked_sub(offset).filter(|&price| price > 0)
+                } else {
+                    config.mid.checked_add(offset)
+                };
+                let quantity = capacity.clamp(0, i64::from(config.quantity)) as u32;
+                let quantity = quantity - quantity % lot;
+                let (Some(price), true) = (price, quantity > 0) else {
+                    break;
+                };
+                ladder.push((side, price, quantity));
+                capacity -= i64::from(quantity);
+            }
+        }
+        ladder
+    }
+
+    // 按阶梯挂出所有报价；风控拒绝的档位只报告并跳过
+    fn quote(&mut self, gateway: &mut OrderGateway) -> Result<(), String> {
+        let position = gateway.position(self.config.product_id);
+        for (side, price, quantity) in self.ladder(position) {
+            let order_id = self.next_order_id(gateway)?;
+            let order = Order {
+                product_id: self.config.product_id,
+                order_id,
+                price,
+                quantity,
+                order_type: side,
+                price_type: ORDER_PRICE_TYPE_LIMIT,
+                submit_time: order_id,
+                expire_time: 0,
+                trigger_price: 0,
+                display_quantity: 0,
+            };
+            match gateway.submit(&order) {
+                Ok(_) => {
+                    self.quoted += 1;
+                    self.quotes.push(Quote { order_id, quantity, filled: 0 });
+                }
+                Err(e) => eprintln!("Quote {} {}@{} rejected: {}",
+                    if side == ORDER_TYPE_BUY { "BUY" } else { "SELL" }, quantity, price, e),
+            }
+        }
+        Ok(())
+    }
+
+    // 撤掉所有未完全成交的报价
+    fn cancel_open(&mut self, gateway: &mut OrderGateway) -> Result<(), String> {
+        for quote in self.quotes.drain(..) {
+            if quote.filled < quote.quantity {
+                gateway.cancel(quote.order_id)?;
+                self.cancelled += 1;
+            }
+        }
+        Ok(())
+    }
+
+    // 记录自己报价的成交，返回是否属于当前报价
+    fn record_fill(&mut self, fill: &Fill) -> bool {
+        let Some(quote) = self.quotes.iter_mut().find(|quote| quote.order_id == fill.order_id) else {
+            return false;
+        };
+        quote.filled += fill.quantity;
+        self.fills += 1;
+        if fill.side == ORDER_TYPE_BUY {
+            self.bought += u64::from(fill.quantity);
+        } else {
+            self.sold += u64::from(fill.quantity);
+        }
+        true
+    }
+
+    fn log_status(&self, gateway: &OrderGateway, event: &str) {
+        println!("🔁 {}: Position={} | Resting={} | Quoted={} | Cancelled={} | Fills={} (Bought {} / Sold {})",
+            event, gateway.position(self.config.product_id), self.quotes.len(),
+            self.quoted, self.cancelled, self.fills, self.bought, self.sold);
+    }
+
+    pub fn print_report(&self, products: &ProductMaster) {
+        let config = &self.config;
+        let price = |price: u64| products.format_price(config.product_id, price);
+
+        println!("\n=============================================");
+        println!("Market Making Report");
+        println!("=============================================");
+        println!("Product: {} | Mid: {} | Spread: {} | Step: {} | Levels: {} x {}",
+            config.product_id, price(config.mid), price(config.spread), price(config.step), config.levels, config.quantity);
+        println!("Quoted: {} | Cancelled: {} | Re-quotes: {}", self.quoted, self.cancelled, self.requotes);
+        println!("Fills: {} | Bought: {} | Sold: {}", self.fills, self.bought, self.sold);
+    }
+}
+
+// 做市：挂出阶梯，看到自己的成交后撤单并按新持仓重挂，退出时撤掉所有报价
+pub fn run(
+    maker: &mut MarketMaker,
+    listener: &mut GroupListener,
+    feed: &mut FeedHandler,
+    gateway: &mut OrderGateway,
+    running: &AtomicBool,
+) -> Result<(), String> {
+    println!("\n=============================================");
+    println!("Market making, Ctrl+C to stop...");
+    println!("=============================================");
+
+    maker.quote(gateway)?;
+    maker.log_status(gateway, "QUOTED");
+
+    let mut delivered = Vec::new();
+    let mut fills = Vec::new();
+
+    while running.load(Ordering::SeqCst) {
+        feed.poll(listener, QUOTE_POLL_INTERVAL, &mut delivered)?;
+
+        // 同一批消息中的多笔成交只重挂一次
+        let mut filled = false;
+        for tagged in delivered.drain(..) {
+            gateway.observe(&tagged.message, &mut fills)?;
+            for fill in fills.drain(..) {
+                println!("{}", fill);
+                filled |= maker.record_fill(&fill);
+            }
+        }
+        if filled {
+            maker.cancel_open(gateway)?;
+            maker.quote(gateway)?;
+            maker.requotes += 1;
+            maker.log_status(gateway, &format!("REQUOTE #{}", maker.requotes));
+        }
+    }
+
+    maker.cancel_open(gateway)?;
+    maker.log_status(gateway, "CANCELLED ALL");
+    Ok(())
+}
@@ -1,34 +1,127 @@
 // src/network.rs
 
-use std::net::{UdpSocket, ToSocketAddrs};
+use std::net::{TcpStream, UdpSocket};
 use std::net::{ IpAddr, Ipv4Addr,SocketAddr};
-use socket2::{Domain, Protocol, Socket, Type};
+use socket2::{Domain, InterfaceIndexOrAddress, Protocol, SockRef, Socket, Type};
 
 
-// 创建并配置发送用的 UDP Socket
-
+// 组播使用的本地网络接口 (由 --interface 解析而来)
+#[derive(Debug, Clone)]
+pub struct MulticastInterface {
+    pub name: Option<String>, // 接口名，例如 eth1；未指定接口时为 None
+    pub addr: Ipv4Addr,       // 接口上的 IPv4 地址，0.0.0.0 表示由系统选择
+    pub index: Option<u32>,   // 接口索引，用于 IP_ADD_MEMBERSHIP (ip_mreqn)
+}
 
-// 通用的 Multicast/Unicast 发送函数
-pub fn send_message(socket: &UdpSocket, addr: &str, message: &[u8]) -> Result<(), String> {
-    match socket.send_to(message, addr) {
-        Ok(bytes_sent) => {
-            if bytes_sent == message.len() {
-                Ok(())
-            } else {
-                Err(format!("Partial send: {} of {} bytes sent.", bytes_sent, message.len()))
-            }
+impl std::fmt::Display for MulticastInterface {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match (&self.name, self.index) {
+            (Some(name), Some(index)) => write!(f, "{} ({}, index {})", name, self.addr, index),
+            (Some(name), None) => write!(f, "{} ({})", name, self.addr),
+            _ => write!(f, "{} (system default)", self.addr),
         }
-        Err(e) => Err(format!("Failed to send message to {}: {}", addr, e)),
     }
 }
 
+// 将接口名或接口 IP 解析为地址和索引
+pub fn resolve_interface(spec: &str) -> Result<MulticastInterface, String> {
+    if let Ok(addr) = spec.parse::<Ipv4Addr>()
+        && addr.is_unspecified() {
+        return Ok(MulticastInterface { name: None, addr, index: None });
+    }
+
+    let interfaces = if_addrs::get_if_addrs()
+        .map_err(|e| format!("Failed to enumerate network interfaces: {}", e))?;
+
+    // 按 IP 或接口名匹配，只考虑 IPv4 地址
+    interfaces.into_iter()
+        .find_map(|iface| match iface.ip() {
+            IpAddr::V4(ip) if iface.name == spec || ip.to_string() == spec => Some(MulticastInterface {
+                name: Some(iface.name),
+                addr: ip,
+                index: iface.index,
+            }),
+            _ => None,
+        })
+        .ok_or_else(|| format!("No IPv4 network interface matches '{}'", spec))
+}
+
+// 可配置的 Socket 选项，None 表示保持操作系统默认值
+#[derive(Debug, Clone, Default)]
+pub struct SocketOptions {
+    pub multicast_ttl: u32,            // IP_MULTICAST_TTL
+    pub multicast_loop: Option<bool>,  // IP_MULTICAST_LOOP
+    pub recv_buffer_size: Option<usize>, // SO_RCVBUF
+    pub send_buffer_size: Option<usize>, // SO_SNDBUF
+    pub reuse_port: bool,              // SO_REUSEPORT (仅监听端)
+    pub tos: Option<u32>,              // IP_TOS (DSCP << 2)
+}
+
+// 应用发送端与监听端共用的选项
+fn apply_common_options(socket: &Socket, options: &SocketOptions) -> Result<(), String> {
+    if let Some(multicast_loop) = options.multicast_loop {
+        socket.set_multicast_loop_v4(multicast_loop)
+            .map_err(|e| format!("Failed to set IP_MULTICAST_LOOP: {}", e))?;
+    }
+    if let Some(size) = options.recv_buffer_size {
+        socket.set_recv_buffer_size(size)
+            .map_err(|e| format!("Failed to set SO_RCVBUF to {}: {}", size, e))?;
+    }
+    if let Some(size) = options.send_buffer_size {
+        socket.set_send_buffer_size(size)
+            .map_err(|e| format!("Failed to set SO_SNDBUF to {}: {}", size, e))?;
+    }
+    Ok(())
+}
+
+// 读取 Socket 上实际生效的选项值，用于启动时打印
+// 注意：Linux 上 SO_RCVBUF/SO_SNDBUF 读回的值是设置值的两倍，并受 rmem_max/wmem_max 限制
+pub fn describe_socket_options<S: std::os::fd::AsFd>(socket: &S) -> String {
+    let sock = SockRef::from(socket);
+    let show = |value: std::io::Result<String>| value.unwrap_or_else(|e| format!("? ({})", e));
+
+    format!("TTL={} | Loop={} | SO_RCVBUF={} | SO_SNDBUF={} | SO_REUSEPORT={} | TOS={}",
+        show(sock.multicast_ttl_v4().map(|v| v.to_string())),
+        show(sock.multicast_loop_v4().map(|v| v.to_string())),
+        show(sock.recv_buffer_size().map(|v| v.to_string())),
+        show(sock.send_buffer_size().map(|v| v.to_string())),
+        show(sock.reuse_port().map(|v| v.to_string())),
+        show(sock.tos().map(|v| format!("{:#04x} (DSCP {})", v, v >> 2))))
+}
+
+// 创建并配置发送用的 UDP Socket
+pub fn create_sender_socket(interface: &MulticastInterface, options: &SocketOptions) -> Result<UdpSocket, String> {
+    let socket = Socket::new(Domain::IPV4, Type::DGRAM, Some(Protocol::UDP))
+        .map_err(|e| format!("Failed to create socket: {}", e))?;
+
+    // Multicast TTL 默认为 1，仅限本地网段；跨路由器时需要设置更高值
+    socket.set_multicast_ttl_v4(options.multicast_ttl)
+        .map_err(|e| format!("Failed to set Multicast TTL to {}: {}", options.multicast_ttl, e))?;
+
+    if let Some(tos) = options.tos {
+        socket.set_tos(tos)
+            .map_err(|e| format!("Failed to set IP_TOS to {:#04x}: {}", tos, e))?;
+    }
+
+    apply_common_options(&socket, options)?;
+
+    let bind_addr: SocketAddr = (Ipv4Addr::UNSPECIFIED, 0).into();
+    socket.bind(&bind_addr.into())
+        .map_err(|e| format!("Failed to bind UDP socket: {}", e))?;
+
+    // 设置 IP_MULTICAST_IF，保证组播从指定网卡发出
+    if !interface.addr.is_unspecified() {
+        socket.set_multicast_if_v4(&interface.addr)
+            .map_err(|e| format!("Failed to set IP_MULTICAST_IF to {}: {}", interface.addr, e))?;
+    }
+
+    Ok(socket.into())
+}
 
-// ... (之前的 send_message 和 create_multicast_socket 保持不变)
-pub fn create_multicast_listener(addr: &str) -> Result<UdpSocket, String> {
-    let mut addrs = addr.to_socket_addrs()
-        .map_err(|e| format!("Invalid multicast address format: {}", e))?;
 
-    let socket_addr = addrs.next().ok_or("No address found")?;
+// ... (之前的 create_multicast_socket 保持不变)
+// source 不为空时做源特定组播 (SSM) 加入，只接收该源主机发出的报文
+pub fn create_multicast_listener(socket_addr: SocketAddr, interface: &MulticastInterface, source: Option<Ipv4Addr>, options: &SocketOptions) -> Result<UdpSocket, String> {
     let ip = socket_addr.ip();
     let port = socket_addr.port();
 
@@ -45,25 +138,35 @@ pub fn create_multicast_listener(addr: &str) -> Result<UdpSocket, String> {
     socket.set_reuse_address(true)
         .map_err(|e| format!("Failed to set SO_REUSEADDR: {}", e))?;
     
-    // 2. 设置 SO_REUSEPORT（在部分 Unix 系统上推荐）
-
-    
-    let multicast_addr = addr.parse::<SocketAddr>().unwrap();
+    // 2. 设置 SO_REUSEPORT（在部分 Unix 系统上推荐），以及缓冲区等选项
+    if options.reuse_port {
+        socket.set_reuse_port(true)
+            .map_err(|e| format!("Failed to set SO_REUSEPORT: {}", e))?;
+    }
+    apply_common_options(&socket, options)?;
 
-    let bind_addr = socket2::SockAddr::from(multicast_addr);
+    let bind_addr = socket2::SockAddr::from(socket_addr);
 
 
-    // 3. 绑定到 0.0.0.0:port
+    // 3. 绑定到组播地址:port（Linux 上可过滤掉同端口其它组的报文）
     socket.bind(&bind_addr)
         .map_err(|e| format!("Failed to bind listener socket to port {}: {}", port, e))?;
 
 
 
 
-    // 4. 加入组播组
+    // 4. 在指定接口上加入组播组（有接口索引时优先使用索引）
+    //    SSM 使用 IP_ADD_SOURCE_MEMBERSHIP，只能按接口地址指定
     if let IpAddr::V4(multicast_ip) = ip {
-        socket.join_multicast_v4(&multicast_ip, &Ipv4Addr::UNSPECIFIED)
-            .map_err(|e| format!("Failed to join multicast group {}: {}", multicast_ip, e))?;
+        let joined = match (source, interface.index) {
+            (Some(source_ip), _) => socket.join_ssm_v4(&source_ip, &multicast_ip, &interface.addr),
+            (None, Some(index)) => socket.join_multicast_v4_n(&multicast_ip, &InterfaceIndexOrAddress::Index(index)),
+            (None, None) => socket.join_multicast_v4(&multicast_ip, &interface.addr),
+        };
+        joined.map_err(|e| match source {
+            Some(source_ip) => format!("Failed to join multicast group {} from source {} on {}: {}", multicast_ip, source_ip, interface, e),
+            None => format!("Failed to join multicast group {} on {}: {}", multicast_ip, interface, e),
+        })?;
     } else {
         return Err("IPv6 multicast not implemented in listener setup.".to_string());
     }
@@ -71,4 +174,63 @@ pub fn create_multicast_listener(addr: &str) -> Result<UdpSocket, String> {
     // 5. 转换为 std::net::UdpSocket
     let listener: UdpSocket = socket.into();
     Ok(listener)
-}
\ No newline at end of file
+}
+
+// 离开由 create_multicast_listener 加入的组播组，参数需与加入时一致
+pub fn leave_multicast_group<S: std::os::fd::AsFd>(socket: &S, group: Ipv4Addr, interface: &MulticastInterface, source: Option<Ipv4Addr>) -> Result<(), String> {
+    let sock = SockRef::from(socket);
+    let left = match (source, interface.index) {
+        (Some(source_ip), _) => sock.leave_ssm_v4(&source_ip, &group, &interface.addr),
+        (None, Some(index)) => sock.leave_multicast_v4_n(&group, &InterfaceIndexOrAddress::Index(index)),
+        (None, None) => sock.leave_multicast_v4(&group, &interface.addr),
+    };
+    left.map_err(|e| format!("Failed to leave multicast group {} on {}: {}", group, interface, e))
+}
+
+// 单播 UDP 监听：绑定到本地端口，由引擎把结果发送到这个已登记的客户端端口
+pub fn create_unicast_listener(socket_addr: SocketAddr, options: &SocketOptions) -> Result<UdpSocket, String> {
+    let domain = if socket_addr.is_ipv4() { Domain::IPV4 } else { Domain::IPV6 };
+    let socket = Socket::new(domain, Type::DGRAM, Some(Protocol::UDP))
+        .map_err(|e| format!("Failed to create socket: {}", e))?;
+
+    socket.set_reuse_address(true)
+        .map_err(|e| format!("Failed to set SO_REUSEADDR: {}", e))?;
+    if options.reuse_port {
+        socket.set_reuse_port(true)
+            .map_err(|e| format!("Failed to set SO_REUSEPORT: {}", e))?;
+    }
+    apply_common_options(&socket, options)?;
+
+    socket.bind(&socket_addr.into())
+        .map_err(|e| format!("Failed to bind listener socket to {}: {}", socket_addr, e))?;
+
+    Ok(socket.into())
+}
+
+// TCP 连接：用于在不支持组播的环境中按固定 50 字节帧收发
+pub fn connect_tcp(socket_addr: SocketAddr, options: &SocketOptions) -> Result<TcpStream, String> {
+    let domain = if socket_addr.is_ipv4() { Domain::IPV4 } else { Domain::IPV6 };
+    let socket = Socket::new(domain, Type::STREAM, Some(Protocol::TCP))
+        .map_err(|e| format!("Failed to create socket: {}", e))?;
+
+    // 小帧低延迟，关闭 Nagle
+    socket.set_nodelay(true)
+        .map_err(|e| format!("Failed to set TCP_NODELAY: {}", e))?;
+    if let Some(tos) = options.tos {
+        socket.set_tos(tos)
+            .map_err(|e| format!("Failed to set IP_TOS to {:#04x}: {}", tos, e))?;
+    }
+    if let Some(size) = options.recv_buffer_size {
+        socket.set_recv_buffer_size(size)
+            .map_err(|e| format!("Failed to set SO_RCVBUF to {}: {}", size, e))?;
+    }
+    if let Some(size) = options.send_buffer_size {
+        socket.set_send_buffer_size(size)
+            .map_err(|e| format!("Failed to set SO_SNDBUF to {}: {}", size, e))?;
+    }
+
+    socket.connect(&socket_addr.into())
+        .map_err(|e| format!("Failed to connect to {}: {}", socket_addr, e))?;
+
+    Ok(socket.into())
+}
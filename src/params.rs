@@ -1,44 +1,256 @@
 // src/params.rs
 
-use clap::{Parser, Subcommand};
-use crate::types::{ORDER_TYPE_BUY, ORDER_TYPE_SELL, ORDER_PRICE_TYPE_LIMIT, ORDER_PRICE_TYPE_MARKET};
+use clap::{Parser, Subcommand, ValueEnum};
+use serde::Deserialize;
+use std::net::{IpAddr, Ipv4Addr};
+use std::path::PathBuf;
+use crate::{DEFAULT_TRADE_ADDR, DEFAULT_STATUS_ADDR, DEFAULT_LISTEN_IP};
+use crate::types::{ORDER_TYPE_BUY, ORDER_TYPE_SELL, ORDER_PRICE_TYPE_NAMES, parse_instance_tag};
+use crate::types::{MSG_TRADE_BROADCAST, MSG_STATUS_BROADCAST};
+use crate::filter::{parse_filter_expr, parse_price_range, Expr};
+use crate::tif::{parse_rfc3339_nanos, parse_session_close};
+use crate::clock::{parse_clock, parse_timestamp, Clock};
+use chrono::NaiveTime;
 
 // --- 命令行参数结构体 ---
 
 #[derive(Parser, Debug)]
 #[command(author, version, about, long_about = None)]
 pub struct Args {
-    /// 交易引擎的组播地址 (IP:Port)，用于发送订单和撤单请求
-    #[arg(long, default_value = "239.0.0.1:5000")]
+    /// 配置文件路径，默认为 ~/.config/trading-client/config.toml
+    #[arg(long, value_name = "FILE", env = "TRADING_CLIENT_CONFIG")]
+    pub config: Option<PathBuf>,
+
+    /// 使用配置文件中的命名环境 (如 dev、uat、prod)。命令行参数和环境变量优先于 profile 中的值
+    #[arg(long, value_name = "NAME", env = "TRADING_CLIENT_PROFILE")]
+    pub profile: Option<String>,
+
+    /// 交易引擎的地址，用于发送订单和撤单请求。
+    /// 支持 mcast://IP:Port (组播)、udp://IP:Port (单播) 和 tcp://Host:Port；不写前缀时按 IP 自动判断
+    #[arg(long, default_value = DEFAULT_TRADE_ADDR, env = "TRADING_CLIENT_TRADE_ADDR")]
     pub trade_addr: String,
 
-    /// 接收交易结果和状态的组播地址 (IP:Port)。默认为 239.0.0.2:5001
-    #[arg(long, default_value = "239.0.0.2:5001")]
-    pub result_addr: String, // <--- 新增字段
+    /// 接收交易结果和状态的地址，可重复或逗号分隔以同时订阅多个组。默认为组播 239.0.0.2:5001。
+    /// udp://0.0.0.0:Port 表示在本地登记端口上接收单播；tcp://Host:Port 表示连接引擎按帧读取
+    #[arg(long, default_value = DEFAULT_STATUS_ADDR, value_delimiter = ',', env = "TRADING_CLIENT_RESULT_ADDR")]
+    pub result_addr: Vec<String>,
+
+    /// 冗余线路 A 的结果地址。与 --line-b 一起使用时同时加入两条线路并去重，取代 --result-addr
+    #[arg(long, value_name = "ADDR", requires = "line_b", conflicts_with = "result_addr")]
+    pub line_a: Option<String>,
+
+    /// 冗余线路 B 的结果地址
+    #[arg(long, value_name = "ADDR", requires = "line_a")]
+    pub line_b: Option<String>,
+
+    /// A/B 仲裁窗口 (毫秒)：等待另一条线路副本的时间，超过即计为该线路丢包
+    #[arg(long, default_value = "500", value_name = "MS")]
+    pub arbitration_window_ms: u64,
+
+    /// 组播使用的网络接口：接口名 (如 eth1) 或接口上的 IPv4 地址。默认 0.0.0.0 由系统选择
+    #[arg(long, default_value = DEFAULT_LISTEN_IP, env = "TRADING_CLIENT_INTERFACE")]
+    pub interface: String,
+
+    /// 源特定组播 (SSM)：只接收来自该引擎主机 IP 的组播 (IP_ADD_SOURCE_MEMBERSHIP)
+    #[arg(long, value_name = "IP")]
+    pub source: Option<Ipv4Addr>,
+
+    /// 允许的来源地址列表 (逗号分隔)，其它来源的报文在接收时丢弃。为空表示不限制
+    #[arg(long, value_name = "IP,...", value_delimiter = ',')]
+    pub allow_source: Vec<IpAddr>,
+
+    /// 重传请求地址 (引擎或本地回放服务)。设置后检测到丢包会请求重传，并把重传帧按序插回实时流
+    #[arg(long, value_name = "ADDR")]
+    pub retransmit_addr: Option<String>,
+
+    /// 等待重传的超时时间 (毫秒)，超时后放弃缺口继续交付
+    #[arg(long, default_value = "500", value_name = "MS")]
+    pub recovery_timeout_ms: u64,
+
+    /// 只处理该引擎实例的消息 (A/B 切换期间)。ASCII 名称 (最多 8 字节) 或 0x 开头的 16 位十六进制
+    #[arg(long, value_name = "TAG", value_parser = parse_instance_tag)]
+    pub primary_instance: Option<[u8; 8]>,
+
+    /// 组播 TTL (IP_MULTICAST_TTL)。1 表示仅限本地网段
+    #[arg(long, default_value = "10", env = "TRADING_CLIENT_TTL")]
+    pub ttl: u32,
+
+    /// 是否回环接收本机发出的组播 (IP_MULTICAST_LOOP)：true 或 false。默认由系统决定
+    #[arg(long, value_name = "BOOL")]
+    pub multicast_loop: Option<bool>,
+
+    /// 接收缓冲区大小 (SO_RCVBUF)，单位字节
+    #[arg(long, value_name = "BYTES")]
+    pub rcvbuf: Option<usize>,
+
+    /// 发送缓冲区大小 (SO_SNDBUF)，单位字节
+    #[arg(long, value_name = "BYTES")]
+    pub sndbuf: Option<usize>,
+
+    /// 监听 Socket 启用 SO_REUSEPORT，允许多个进程监听同一端口
+    #[arg(long)]
+    pub reuse_port: bool,
+
+    /// 发送报文的 DSCP 标记 (0-63)，写入 IP_TOS 的高 6 位
+    #[arg(long, value_parser = clap::value_parser!(u8).range(0..=63), conflicts_with = "tos")]
+    pub dscp: Option<u8>,
+
+    /// 发送报文的原始 IP_TOS 字节 (0-255)
+    #[arg(long)]
+    pub tos: Option<u8>,
     
+    /// 下单前风控配置文件 (TOML)。不指定时使用 profile 中的 risk 配置，都没有则不做风控检查
+    #[arg(long, value_name = "FILE", env = "TRADING_CLIENT_RISK_CONFIG")]
+    pub risk_config: Option<PathBuf>,
+
+    /// 本地状态目录 (风控状态、持仓、订单日志等)。默认为系统数据目录下的 trading-client
+    #[arg(long, value_name = "DIR", env = "TRADING_CLIENT_STATE_DIR")]
+    pub state_dir: Option<PathBuf>,
+
+    /// 产品主数据文件 (TOML)：代码、最小变动价位、每手数量和价格小数位。
+    /// 默认为 ~/.config/trading-client/products.toml (存在时)
+    #[arg(long = "products", value_name = "FILE", env = "TRADING_CLIENT_PRODUCTS")]
+    pub product_file: Option<PathBuf>,
+
+    /// 监听输出格式：text (默认) 或 json (每行一个 JSON 对象)
+    #[arg(long, value_enum, default_value = "text", env = "TRADING_CLIENT_OUTPUT_FORMAT")]
+    pub output_format: OutputFormat,
+
+    /// 下单时钟：system、fixed:<T>、offset:<纳秒> 或 simulated:<T>[:<步长纳秒>]，T 为 Unix 纳秒或 RFC 3339。
+    /// 用于回归测试生成可复现的 submit_time 和订单 ID
+    #[arg(long, default_value = "system", value_parser = parse_clock, env = "TRADING_CLIENT_CLOCK")]
+    pub clock: Clock,
+
+    /// 帧显示级别：none 不显示发送和解码失败的帧，hex 显示按字段着色的十六进制 (默认)，fields 另加逐字段解码
+    #[arg(long, value_enum, default_value = "hex", value_name = "LEVEL", env = "TRADING_CLIENT_FRAMES")]
+    pub frames: FrameDisplay,
+
+    #[command(flatten)]
+    pub filter: FilterArgs,
+
     // 提交订单的子命令
     #[clap(subcommand)]
     pub command: Command,
 }
 
 
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum OutputFormat {
+    Text,
+    Json,
+}
+
+// 监听输出的过滤选项 (对所有监听模式生效)
+#[derive(Parser, Debug)]
+pub struct FilterArgs {
+    /// 只显示这些产品 ID 的消息 (逗号分隔)
+    #[arg(long = "product", value_name = "ID,...", value_delimiter = ',')]
+    pub products: Vec<u16>,
+
+    /// 只显示某类消息：trade 或 status
+    #[arg(long = "type", value_name = "TYPE", value_parser = parse_message_type)]
+    pub msg_type: Option<u8>,
+
+    /// 只显示买方或卖方订单 ID 为该值的成交
+    #[arg(long = "order-id", value_name = "ID")]
+    pub order_id: Option<u64>,
+
+    /// 只显示数量不小于该值的成交
+    #[arg(long, value_name = "QTY")]
+    pub min_qty: Option<u32>,
+
+    /// 只显示价格在区间内的成交：LOW..HIGH、LOW.. 或 ..HIGH (闭区间)
+    #[arg(long, value_name = "RANGE", value_parser = parse_price_range)]
+    pub price_range: Option<(Option<u64>, Option<u64>)>,
+
+    /// 过滤表达式，例如 'price > 100 && quantity >= 10'。支持 == != > >= < <=、&&、||、! 和括号
+    #[arg(long, value_name = "EXPR", value_parser = parse_filter_expr)]
+    pub filter: Option<Expr>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum FrameDisplay {
+    None,
+    Hex,
+    Fields,
+}
+
 #[derive(Subcommand, Debug)]
 pub enum Command {
     /// 提交一个新的订单
     Submit(SubmitArgs),
     /// 撤销一个订单
     Cancel(CancelArgs),
+    /// 只监听结果和状态，不发送任何请求
+    Listen,
+    /// 显示由自己订单成交计算的持仓、均价和盈亏 (不监听)
+    Positions,
+    /// 算法执行母单：TWAP (按时间均分) 或 VWAP (按市场成交量比例) 拆分子单，结束时撤掉剩余子单并报告滑点
+    Execute(ExecuteArgs),
+    /// 做市：围绕中间价维持买卖挂单阶梯，看到自己的成交后撤单重挂，直到 Ctrl+C (用于引擎压力测试)
+    MarketMake(MarketMakeArgs),
+    /// 运行 Rhai 场景脚本 (submit、cancel、wait_for、sleep、assert)，断言失败时以错误退出
+    RunScenario(RunScenarioArgs),
+    /// 引擎回归测试：按 YAML 发送订单和撤单，检查期望的成交和统计广播，输出 JUnit XML 报告
+    TestEngine(TestEngineArgs),
+    /// 协议调试：发送手工构造的原始帧，或逐字段解码一个帧
+    Raw {
+        #[clap(subcommand)]
+        command: RawCommand,
+    },
+    /// 查询本地订单日志 (不监听)
+    Orders {
+        #[clap(subcommand)]
+        command: OrdersCommand,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+pub enum OrdersCommand {
+    /// 列出日志中的所有订单及成交状态
+    List,
+    /// 显示一个订单的明细、原始帧、撤单和成交
+    Show {
+        /// 订单 ID
+        order_id: u64,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+pub enum RawCommand {
+    /// 发送原始帧 (不做风控检查，不写订单日志)，然后监听结果
+    Send {
+        /// 帧内容，100 个十六进制字符 (50 字节)，可带 0x 前缀
+        #[arg(long)]
+        hex: String,
+
+        /// 发送前重新计算并写入校验和 (字节 0)。默认按原样发送，校验和错误时只警告
+        #[arg(long)]
+        fix_checksum: bool,
+    },
+    /// 逐字段解码一个帧：偏移、字段名、原始字节和值 (不连接网络)
+    Decode {
+        /// 帧内容，100 个十六进制字符 (50 字节)，可带 0x 前缀
+        #[arg(long)]
+        hex: String,
+    },
 }
 
 #[derive(Parser, Debug)]
 pub struct SubmitArgs {
-    /// 产品 ID (u16)
+    /// 产品 ID (u16)。不指定时使用 profile 中的 default_product
+    #[arg(long, conflicts_with = "symbol")]
+    pub product_id: Option<u16>,
+
+    /// 产品代码 (按产品主数据查找产品 ID)
     #[arg(long)]
-    pub product_id: u16,
+    pub symbol: Option<String>,
 
-    /// 价格 (u64)
+    /// 价格。产品在主数据中时按显示单位 (如 12.345) 换算，否则为线上 u64。
+    /// limit 和 stop-limit 必填，market 和 stop 可省略
     #[arg(long)]
-    pub price: u64,
+    pub price: Option<String>,
 
     /// 数量 (u32)
     #[arg(long)]
@@ -48,13 +260,153 @@ pub struct SubmitArgs {
     #[arg(long, value_parser = parse_order_type)]
     pub order_type: u8,
 
-    /// 价格类型：limit 或 market
+    /// 价格类型：limit、market、stop (触发后市价) 或 stop-limit (触发后限价)
     #[arg(long, value_parser = parse_price_type)]
     pub price_type: u8,
 
+    /// stop / stop-limit 的触发价 (单位同 --price)
+    #[arg(long, value_name = "PRICE")]
+    pub trigger_price: Option<String>,
+
+    /// 冰山单：每次只在订单簿上显示的数量
+    #[arg(long, value_name = "QTY")]
+    pub display_qty: Option<u32>,
+
+    /// 只做挂单 (post-only)：会立即成交时由引擎拒绝
+    #[arg(long)]
+    pub post_only: bool,
+
+    /// 引擎不支持原生止损单时，由客户端监听成交行情，在触发后发出市价/限价单
+    #[arg(long)]
+    pub synthetic_stop: bool,
+
     /// 订单过期时间，以秒为单位 (GTC/0 means never expire)
     #[arg(long, default_value = "0")]
     pub expire: u64,
+
+    /// 有效期类型：gtc、day (当日收盘)、gtd (指定时间)、ioc、fok
+    #[arg(long, value_enum, default_value = "gtc")]
+    pub tif: TimeInForce,
+
+    /// GTD 的绝对过期时间 (RFC 3339)，例如 2026-10-18T16:30:00+08:00
+    #[arg(long, value_name = "TIME", value_parser = parse_rfc3339_nanos, conflicts_with = "expire")]
+    pub expire_at: Option<u64>,
+
+    /// DAY 订单的收盘时间 (本地时间 HH:MM[:SS])。默认使用 profile 中的 session_close 或 17:00
+    #[arg(long, value_name = "HH:MM", value_parser = parse_session_close, env = "TRADING_CLIENT_SESSION_CLOSE")]
+    pub session_close: Option<NaiveTime>,
+
+    /// 覆盖 submit_time (Unix 纳秒或 RFC 3339)，不从 --clock 读取
+    #[arg(long, value_name = "T", value_parser = parse_timestamp)]
+    pub submit_time: Option<u64>,
+
+    /// 覆盖订单 ID。默认使用 submit_time
+    #[arg(long)]
+    pub order_id: Option<u64>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum TimeInForce {
+    Gtc,
+    Day,
+    Gtd,
+    Ioc,
+    Fok,
+}
+
+#[derive(Parser, Debug)]
+pub struct ExecuteArgs {
+    /// 产品 ID (u16)。不指定时使用 profile 中的 default_product
+    #[arg(long, conflicts_with = "symbol")]
+    pub product_id: Option<u16>,
+
+    /// 产品代码 (按产品主数据查找产品 ID)
+    #[arg(long)]
+    pub symbol: Option<String>,
+
+    /// 母单总数量
+    #[arg(long)]
+    pub quantity: u32,
+
+    /// 方向：buy 或 sell
+    #[arg(long, value_parser = parse_order_type)]
+    pub order_type: u8,
+
+    /// 执行算法：twap 或 vwap
+    #[arg(long, value_enum, default_value = "twap")]
+    pub algo: Algo,
+
+    /// 执行时间窗口，以秒为单位
+    #[arg(long, value_name = "SECS")]
+    pub duration: u64,
+
+    /// TWAP 子单数量
+    #[arg(long, default_value = "10")]
+    pub slices: u32,
+
+    /// VWAP 参与率 (%)：子单累计数量跟随观察到的市场成交量的比例
+    #[arg(long, default_value = "10", value_name = "PERCENT")]
+    pub participation: f64,
+
+    /// 子单限价 (单位同 submit --price)。不指定时子单为市价单
+    #[arg(long, value_name = "PRICE")]
+    pub limit_price: Option<String>,
+}
+#[derive(Parser, Debug)]
+pub struct MarketMakeArgs {
+    /// 产品 ID (u16)。不指定时使用 profile 中的 default_product
+    #[arg(long, conflicts_with = "symbol")]
+    pub product_id: Option<u16>,
+
+    /// 产品代码 (按产品主数据查找产品 ID)
+    #[arg(long)]
+    pub symbol: Option<String>,
+
+    /// 中间价 (单位同 submit --price)
+    #[arg(long, value_name = "PRICE")]
+    pub mid: String,
+
+    /// 第一档买卖价与中间价的距离 (单位同 --mid)
+    #[arg(long, value_name = "PRICE")]
+    pub spread: String,
+
+    /// 相邻两档之间的价差 (单位同 --mid)
+    #[arg(long, value_name = "PRICE")]
+    pub step: String,
+
+    /// 每一边的档位数
+    #[arg(long, default_value = "3")]
+    pub levels: u32,
+
+    /// 每一档的挂单数量
+    #[arg(long)]
+    pub quantity: u32,
+
+    /// 库存上限 (持仓绝对值)：挂单全部成交后持仓也不会超过该值，达到上限的一边停止报价
+    #[arg(long, value_name = "QTY")]
+    pub max_position: u32,
+}
+
+#[derive(Parser, Debug)]
+pub struct RunScenarioArgs {
+    /// 场景脚本路径 (.rhai)
+    pub script: PathBuf,
+}
+
+#[derive(Parser, Debug)]
+pub struct TestEngineArgs {
+    /// 测试场景文件路径 (.yaml)
+    pub scenario: PathBuf,
+
+    /// JUnit XML 报告输出路径
+    #[arg(long, value_name = "FILE", default_value = "test-engine.xml")]
+    pub junit: PathBuf,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum Algo {
+    Twap,
+    Vwap,
 }
 
 #[derive(Parser, Debug)]
@@ -65,7 +417,7 @@ pub struct CancelArgs {
 }
 
 // 辅助解析函数
-fn parse_order_type(s: &str) -> Result<u8, String> {
+pub fn parse_order_type(s: &str) -> Result<u8, String> {
     match s.to_lowercase().as_str() {
         "buy" => Ok(ORDER_TYPE_BUY),
         "sell" => Ok(ORDER_TYPE_SELL),
@@ -73,10 +425,26 @@ fn parse_order_type(s: &str) -> Result<u8, String> {
     }
 }
 
-fn parse_price_type(s: &str) -> Result<u8, String> {
+pub fn parse_price_type(s: &str) -> Result<u8, String> {
+    // 名称取自 ORDER_PRICE_TYPE_NAMES，大小写不敏感，'-' 与 '_' 等价
+    let name = s.to_uppercase().replace('-', "_");
+    ORDER_PRICE_TYPE_NAMES.iter()
+        .find(|&&(_, known)| known == name)
+        .map(|&(value, _)| value)
+        .ok_or_else(|| format!("Invalid price type: {}. Must be 'limit', 'market', 'stop' or 'stop-limit'", s))
+}
+
+fn parse_message_type(s: &str) -> Result<u8, String> {
     match s.to_lowercase().as_str() {
-        "limit" => Ok(ORDER_PRICE_TYPE_LIMIT),
-        "market" => Ok(ORDER_PRICE_TYPE_MARKET),
-        _ => Err(format!("Invalid price type: {}. Must be 'limit' or 'market'", s)),
+        "trade" => Ok(MSG_TRADE_BROADCAST),
+        "status" => Ok(MSG_STATUS_BROADCAST),
+        _ => Err(format!("Invalid message type: {}. Must be 'trade' or 'status'", s)),
     }
-}
\ No newline at end of file
+}
+
+// 本地状态目录：--state-dir，否则为系统数据目录 (如 ~/.local/share/trading-client)
+pub fn resolve_state_dir(state_dir: Option<PathBuf>) -> PathBuf {
+    state_dir
+        .or_else(|| dirs::data_local_dir().map(|dir| dir.join("trading-client")))
+        .unwrap_or_else(|| PathBuf::from(".trading-client"))
+}
@@ -0,0 +1,389 @@
+// src/position.rs
+
+use std::fmt;
+use std::fs;
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+
+use serde::{Deserialize, Serialize};
+
+use crate::encoding::BroadcastMessage;
+use crate::types::{Order, ORDER_TYPE_BUY};
+
+// 状态文件最多每隔这么久写一次，退出时 (OrderGateway::close) 总会写入
+const SAVE_INTERVAL: Duration = Duration::from_secs(1);
+// 撤单、IOC/FOK 和到期的订单再保留一段时间，以便识别路上迟到的成交
+const CLOSED_ORDER_GRACE_NANOS: u64 = 10_000_000_000;
+// 最多保留的自己订单数，超过后丢弃最早的
+const MAX_OWN_ORDERS: usize = 10_000;
+
+// 自己发出的订单，用于从成交回报中识别自己的成交
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct OwnOrder {
+    order_id: u64,
+    product_id: u16,
+    side: u8, // ORDER_TYPE_BUY / ORDER_TYPE_SELL
+    #[serde(default)]
+    remaining: u32,
+    #[serde(default)]
+    closes_at: u64, // 此后引擎不再成交 (撤单、IOC/FOK 或到期)，0 表示仍在挂单
+}
+
+impl OwnOrder {
+    fn is_done(&self, now: u64) -> bool {
+        self.remaining == 0
+            || (self.closes_at != 0 && now >= self.closes_at.saturating_add(CLOSED_ORDER_GRACE_NANOS))
+    }
+}
+
+// 单个产品的持仓：数量为正表示多头，为负表示空头
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+struct Position {
+    product_id: u16,
+    quantity: i64,
+    avg_cost: f64,
+    realized_pnl: f64,
+    last_price: Option<u64>, // 行情中看到的最近成交价，用于计算浮动盈亏
+}
+
+impl Position {
+    fn unrealized_pnl(&self) -> Option<f64> {
+        self.last_price.map(|last| self.quantity as f64 * (last as f64 - self.avg_cost))
+    }
+
+    // 按成交更新持仓：同向加仓更新均价，反向先平仓计算已实现盈亏，剩余部分按成交价开新仓
+    fn apply_fill(&mut self, buy: bool, price: u64, quantity: u32) {
+        let price = price as f64;
+        let signed = if buy { i64::from(quantity) } else { -i64::from(quantity) };
+
+        if self.quantity == 0 || self.quantity.signum() == signed.signum() {
+            let held = self.quantity.abs() as f64;
+            self.avg_cost = (held * self.avg_cost + f64::from(quantity) * price) / (held + f64::from(quantity));
+            self.quantity += signed;
+            return;
+        }
+
+        let closed = self.quantity.abs().min(signed.abs());
+        self.realized_pnl += closed as f64 * (price - self.avg_cost) * self.quantity.signum() as f64;
+        self.quantity += signed;
+        if self.quantity == 0 {
+            self.avg_cost = 0.0;
+        } else if self.quantity.signum() == signed.signum() {
+            self.avg_cost = price;
+        }
+    }
+}
+
+// 自己订单的一笔成交
+#[derive(Debug, Clone)]
+pub struct Fill {
+    pub order_id: u64,
+    pub product_id: u16,
+    pub side: u8,
+    pub price: u64,
+    pub quantity: u32,
+    pub position: i64,
+    pub realized_pnl: f64,
+}
+
+impl fmt::Display for Fill {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let side = if self.side == ORDER_TYPE_BUY { "BUY" } else { "SELL" };
+        write!(
+            f,
+            "💰 FILL: OrderID={} | Product={} | Side={} | Price={} | Qty={} | Position={} | Realized={:.2}",
+            self.order_id, self.product_id, side, self.price, self.quantity, self.position, self.realized_pnl
+        )
+    }
+}
+
+impl Fill {
+    pub fn to_json(&self) -> serde_json::Value {
+        serde_json::json!({
+            "type": "fill",
+            "order_id": self.order_id,
+            "product_id": self.product_id,
+            "side": if self.side == ORDER_TYPE_BUY { "buy" } else { "sell" },
+            "price": self.price,
+            "quantity": self.quantity,
+            "position": self.position,
+            "realized_pnl": self.realized_pnl,
+        })
+    }
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct PositionState {
+    #[serde(default)]
+    own_orders: Vec<OwnOrder>,
+    #[serde(default)]
+    positions: Vec<Position>,
+}
+
+// 持仓和盈亏，跨进程持久化
+pub struct PositionBook {
+    state: PositionState,
+    state_path: PathBuf,
+    dirty: bool,
+    last_saved: Option<Instant>,
+}
+
+impl PositionBook {
+    pub fn load(state_path: PathBuf) -> Result<Self, String> {
+        let state = match fs::read_to_string(&state_path) {
+            Ok(text) => toml::from_str(&text)
+                .map_err(|e| format!("Invalid position state {}: {}", state_path.display(), e))?,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => PositionState::default(),
+            Err(e) => return Err(format!("Failed to read position state {}: {}", state_path.display(), e)),
+        };
+        Ok(PositionBook { state, state_path, dirty: false, last_saved: None })
+    }
+
+    pub fn save(&mut self) -> Result<(), String> {
+        if let Some(dir) = self.state_path.parent() {
+            fs::create_dir_all(dir)
+                .map_err(|e| format!("Failed to create state directory {}: {}", dir.display(), e))?;
+        }
+        let text = toml::to_string(&self.state)
+            .map_err(|e| format!("Failed to serialize position state: {}", e))?;
+        fs::write(&self.state_path, text)
+            .map_err(|e| format!("Failed to write position state {}: {}", self.state_path.display(), e))?;
+        self.dirty = false;
+        self.last_saved = Some(Instant::now());
+        Ok(())
+    }
+
+    // 有未保存的变化且距上次写入超过 SAVE_INTERVAL 时写入
+    pub fn save_if_due(&mut self) -> Result<(), String> {
+        if self.dirty && self.last_saved.is_none_or(|saved| saved.elapsed() >= SAVE_INTERVAL) {
+            self.save()?;
+        }
+        Ok(())
+    }
+
+    // 丢弃不会再有成交的订单，并限制保留的数量
+    fn prune(&mut self, now: u64) {
+        let orders = &mut self.state.own_orders;
+        orders.retain(|order| !order.is_done(now));
+        if orders.len() > MAX_OWN_ORDERS {
+            orders.drain(..orders.len() - MAX_OWN_ORDERS);
+        }
+    }
+
+    pub fn record_submit(&mut self, order: &Order, now: u64) {
+        // 市价单和 IOC/FOK 在引擎上立即结束，GTD 到期后结束
+        let closes_at = if order.is_market() || order.is_immediate() { now } else { order.expire_time };
+        self.state.own_orders.push(OwnOrder {
+            order_id: order.order_id,
+            product_id: order.product_id,
+            side: order.order_type,
+            remaining: order.quantity,
+            closes_at,
+        });
+        self.prune(now);
+        self.dirty = true;
+    }
+
+    pub fn record_cancel(&mut self, order_id: u64, now: u64) {
+        if let Some(order) = self.state.own_orders.iter_mut().find(|order| order.order_id == order_id)
+            && (order.closes_at == 0 || order.closes_at > now) {
+            order.closes_at = now;
+            self.dirty = true;
+        }
+    }
+
+    fn position_mut(&mut self, product_id: u16) -> &mut Position {
+        let index = match self.state.positions.iter().position(|p| p.product_id == product_id) {
+            Some(index) => index,
+            None => {
+                self.state.positions.push(Position { product_id, ..Position::default() });
+                self.state.positions.len() - 1
+            }
+        };
+        &mut self.state.positions[index]
+    }
+
+    // 行情更新：标记最近成交价，并把自己订单的成交计入持仓 (自成交时买卖两边都会计入)
+    pub fn observe(&mut self, message: &BroadcastMessage, fills: &mut Vec<Fill>) {
+        let BroadcastMessage::Trade(result) = message else {
+            return;
+        };
+
+        let own: Vec<OwnOrder> = self.state.own_orders.iter()
+            .filter(|order| order.order_id == result.buy_order_id || order.order_id == result.sell_order_id)
+            .filter(|order| order.product_id == result.product_id)
+            .cloned()
+            .collect();
+        let has_position = self.state.positions.iter().any(|p| p.product_id == result.product_id);
+        if own.is_empty() && !has_position {
+            return;
+        }
+
+        for order in self.state.own_orders.iter_mut().filter(|order| own.iter().any(|o| o.order_id == order.order_id)) {
+            order.remaining = order.remaining.saturating_sub(result.quantity);
+        }
+        self.state.own_orders.retain(|order| order.remaining > 0);
+        self.dirty = true;
+
+        let position = self.position_mut(result.product_id);
+        position.last_price = Some(result.price);
+        for order in own {
+            position.apply_fill(order.side == ORDER_TYPE_BUY, result.price, result.quantity);
+            fills.push(Fill {
+                order_id: order.order_id,
+                product_id: result.product_id,
+                side: order.side,
+                price: result.price,
+                quantity: result.quantity,
+                position: position.quantity,
+                realized_pnl: position.realized_pnl,
+            });
+        }
+    }
+
+    pub fn last_price(&self, product_id: u16) -> Option<u64> {
+        self.state.positions.iter()
+            .find(|p| p.product_id == product_id)
+            .and_then(|p| p.last_price)
+    }
+
+    // 当前持仓数量 (没有持仓记录时为 0)
+    pub fn quantity(&self, product_id: u16) -> i64 {
+        self.state.positions.iter()
+            .find(|p| p.product_id == product_id)
+            .map(|p| p.quantity)
+            .unwrap_or(0)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.state.positions.is_empty()
+    }
+
+    pub fn print(&self) {
+        println!("\n=============================================");
+        println!("Positions ({})", self.state_path.display());
+        println!("=============================================");
+        if self.state.positions.is_empty() {
+            println!("  (none)");
+            return;
+        }
+
+        let mut positions: Vec<&Position> = self.state.positions.iter().collect();
+        positions.sort_by_key(|p| p.product_id);
+        println!("{:>8} {:>10} {:>14} {:>12} {:>14} {:>14}", "Product", "Position", "AvgCost", "Last", "Realized", "Unrealized");
+        let (mut realized, mut unrealized) = (0.0, 0.0);
+        for p in positions {
+            let last = p.last_price.map(|price| price.to_string()).unwrap_or_else(|| "-".to_string());
+            let open = p.unrealized_pnl();
+            let open_text = open.map(|pnl| format!("{:.2}", pnl)).unwrap_or_else(|| "-".to_string());
+            println!("{:>8} {:>10} {:>14.2} {:>12} {:>14.2} {:>14}", p.product_id, p.quantity, p.avg_cost, last, p.realized_pnl, open_text);
+            realized += p.realized_pnl;
+            unrealized += open.unwrap_or(0.0);
+        }
+        println!("Total: Realized={:.2} | Unrealized={:.2} | P&L={:.2}", realized, unrealized, realized + unrealized);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{MatchResult, ORDER_PRICE_TYPE_LIMIT, ORDER_TIF_IOC, ORDER_TYPE_SELL};
+
+    const NOW: u64 = 1_700_000_000_000_000_000;
+
+    fn book() -> PositionBook {
+        PositionBook { state: PositionState::default(), state_path: PathBuf::from("unused-positions.toml"), dirty: false, last_saved: None }
+    }
+
+    fn order(order_id: u64, side: u8, quantity: u32) -> Order {
+        Order {
+            product_id: 1,
+            order_id,
+            price: 100,
+            quantity,
+            order_type: side,
+            price_type: ORDER_PRICE_TYPE_LIMIT,
+            submit_time: NOW,
+            expire_time: 0,
+            trigger_price: 0,
+            display_quantity: 0,
+        }
+    }
+
+    fn trade(buy_order_id: u64, sell_order_id: u64, price: u64, quantity: u32) -> BroadcastMessage {
+        BroadcastMessage::Trade(MatchResult {
+            instance_tag: [0; 8],
+            product_id: 1,
+            buy_order_id,
+            sell_order_id,
+            price,
+            quantity,
+            trade_network_time: 0,
+            internal_match_time: 0,
+            sequence: 0,
+        })
+    }
+
+    fn own_ids(book: &PositionBook) -> Vec<u64> {
+        book.state.own_orders.iter().map(|order| order.order_id).collect()
+    }
+
+    #[test]
+    fn fills_update_position_and_release_filled_orders() {
+        let mut book = book();
+        book.record_submit(&order(1, ORDER_TYPE_BUY, 10), NOW);
+        book.record_submit(&order(2, ORDER_TYPE_SELL, 10), NOW);
+
+        let mut fills = Vec::new();
+        book.observe(&trade(1, 99, 100, 4), &mut fills);
+        assert_eq!(book.quantity(1), 4);
+        assert_eq!(own_ids(&book), [1, 2]);
+
+        book.observe(&trade(1, 99, 100, 6), &mut fills);
+        book.observe(&trade(99, 2, 110, 10), &mut fills);
+        assert_eq!(fills.len(), 3);
+        assert_eq!(book.quantity(1), 0);
+        assert_eq!(fills[2].realized_pnl, 100.0);
+        assert!(own_ids(&book).is_empty());
+    }
+
+    #[test]
+    fn closed_orders_kept_for_late_fills() {
+        let mut book = book();
+        book.record_submit(&order(1, ORDER_TYPE_BUY, 10), NOW);
+        let mut ioc = order(2, ORDER_TYPE_BUY, 10);
+        ioc.price_type |= ORDER_TIF_IOC;
+        book.record_submit(&ioc, NOW);
+        book.record_cancel(1, NOW);
+
+        // 撤单后路上的成交仍计入持仓
+        let mut fills = Vec::new();
+        book.observe(&trade(1, 99, 100, 3), &mut fills);
+        assert_eq!(book.quantity(1), 3);
+
+        book.record_submit(&order(3, ORDER_TYPE_BUY, 10), NOW + CLOSED_ORDER_GRACE_NANOS - 1);
+        assert_eq!(own_ids(&book), [1, 2, 3]);
+        book.record_submit(&order(4, ORDER_TYPE_BUY, 10), NOW + CLOSED_ORDER_GRACE_NANOS);
+        assert_eq!(own_ids(&book), [3, 4]);
+    }
+
+    #[test]
+    fn expired_orders_pruned_after_grace() {
+        let mut book = book();
+        let mut gtd = order(1, ORDER_TYPE_BUY, 10);
+        gtd.expire_time = NOW + 5;
+        book.record_submit(&gtd, NOW);
+        book.record_submit(&order(2, ORDER_TYPE_BUY, 10), NOW + 5 + CLOSED_ORDER_GRACE_NANOS);
+        assert_eq!(own_ids(&book), [2]);
+    }
+
+    #[test]
+    fn own_orders_are_capped() {
+        let mut book = book();
+        for order_id in 0..=MAX_OWN_ORDERS as u64 + 1 {
+            book.record_submit(&order(order_id, ORDER_TYPE_BUY, 1), NOW);
+        }
+        assert_eq!(book.state.own_orders.len(), MAX_OWN_ORDERS);
+        assert_eq!(book.state.own_orders[0].order_id, 2);
+    }
+}
@@ -0,0 +1,263 @@
+// src/product.rs
+
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use serde::Deserialize;
+
+use crate::encoding::BroadcastMessage;
+use crate::types::{format_instance_tag, SEQUENCE_NONE};
+
+// --- 产品主数据 (TOML) ---
+//
+// [[products]]
+// product_id = 7
+// symbol = "ABC"
+// price_decimals = 3   # 线上价格 = 显示价格 * 10^3
+// tick_size = 0.005    # 显示单位
+// lot_size = 100
+
+// 10^19 是 u64 能表示的最大的 10 的幂
+const MAX_PRICE_DECIMALS: u32 = 19;
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ProductInfo {
+    pub product_id: u16,
+    pub symbol: String,
+    #[serde(default)]
+    pub price_decimals: u32,
+    pub tick_size: Option<f64>,
+    #[serde(default = "default_lot_size")]
+    pub lot_size: u32,
+}
+
+fn default_lot_size() -> u32 {
+    1
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ProductFile {
+    #[serde(default)]
+    products: Vec<ProductInfo>,
+}
+
+impl ProductInfo {
+    fn scale(&self) -> u64 {
+        10u64.pow(self.price_decimals)
+    }
+
+    // 最小价格变动，线上单位
+    pub fn tick_units(&self) -> Option<u64> {
+        self.tick_size.map(|tick| (tick * self.scale() as f64).round() as u64)
+    }
+
+    // 显示价格 (如 "12.345") 转为线上 u64，不做浮点运算
+    pub fn parse_price(&self, text: &str) -> Result<u64, String> {
+        let (whole, fraction) = text.split_once('.').unwrap_or((text, ""));
+        let digits = |part: &str| part.bytes().all(|b| b.is_ascii_digit());
+        if (whole.is_empty() && fraction.is_empty()) || !digits(whole) || !digits(fraction) {
+            return Err(format!("Invalid price for {}: {}", self.symbol, text));
+        }
+        if fraction.len() > self.price_decimals as usize {
+            return Err(format!("Price {} has more than {} decimals for {}", text, self.price_decimals, self.symbol));
+        }
+        let padded = format!("{}{:0<width$}", whole, fraction, width = self.price_decimals as usize);
+        padded.parse::<u64>()
+            .map_err(|_| format!("Invalid price for {}: {}", self.symbol, text))
+    }
+
+    pub fn format_price(&self, price: u64) -> String {
+        if self.price_decimals == 0 {
+            return price.to_string();
+        }
+        format!("{}.{:0width$}", price / self.scale(), price % self.scale(), width = self.price_decimals as usize)
+    }
+
+    // 价格必须是最小变动的整数倍，数量必须是整手
+    pub fn validate(&self, price: u64, quantity: u32) -> Result<(), String> {
+        if let Some(tick) = self.tick_units()
+            && tick > 0 && !price.is_multiple_of(tick) {
+            return Err(format!("Price {} is not a multiple of tick size {} for {}",
+                self.format_price(price), self.format_price(tick), self.symbol));
+        }
+        if self.lot_size > 1 && !quantity.is_multiple_of(self.lot_size) {
+            return Err(format!("Quantity {} is not a multiple of lot size {} for {}", quantity, self.lot_size, self.symbol));
+        }
+        Ok(())
+    }
+}
+
+// 产品 ID <-> 代码、价格精度
+#[derive(Debug, Default)]
+pub struct ProductMaster {
+    products: Vec<ProductInfo>,
+}
+
+// 默认产品主数据文件：~/.config/trading-client/products.toml (不存在时忽略)
+pub fn default_products_path() -> Option<PathBuf> {
+    dirs::config_dir()
+        .map(|dir| dir.join("trading-client").join("products.toml"))
+        .filter(|path| path.exists())
+}
+
+impl ProductMaster {
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let text = fs::read_to_string(path)
+            .map_err(|e| format!("Failed to read product file {}: {}", path.display(), e))?;
+        let file: ProductFile = toml::from_str(&text)
+            .map_err(|e| format!("Invalid product file {}: {}", path.display(), e))?;
+        if let Some(info) = file.products.iter().find(|p| p.price_decimals > MAX_PRICE_DECIMALS) {
+            return Err(format!("Invalid product file {}: price_decimals {} for {} exceeds {}",
+                path.display(), info.price_decimals, info.symbol, MAX_PRICE_DECIMALS));
+        }
+        Ok(ProductMaster { products: file.products })
+    }
+
+    pub fn by_id(&self, product_id: u16) -> Option<&ProductInfo> {
+        self.products.iter().find(|p| p.product_id == product_id)
+    }
+
+    pub fn by_symbol(&self, symbol: &str) -> Result<&ProductInfo, String> {
+        self.products.iter()
+            .find(|p| p.symbol.eq_ignore_ascii_case(symbol))
+            .ok_or_else(|| format!("Unknown symbol: {}", symbol))
+    }
+
+    // 按 --symbol 或 --product-id (再退回 profile 的 default_product) 确定产品 ID
+    pub fn resolve(&self, symbol: Option<&str>, product_id: Option<u16>) -> Result<u16, String> {
+        match symbol {
+            Some(symbol) => Ok(self.by_symbol(symbol)?.product_id),
+            None => product_id
+                .ok_or_else(|| "--product-id or --symbol is required (or set default_product in the profile)".to_string()),
+        }
+    }
+
+    // 有主数据时按显示单位换算，否则为线上 u64
+    pub fn parse_price(&self, product_id: u16, text: &str) -> Result<u64, String> {
+        match self.by_id(product_id) {
+            Some(info) => info.parse_price(text),
+            None => text.parse::<u64>().map_err(|_| format!("Invalid price: {}", text)),
+        }
+    }
+
+    pub fn format_price(&self, product_id: u16, price: u64) -> String {
+        match self.by_id(product_id) {
+            Some(info) => info.format_price(price),
+            None => price.to_string(),
+        }
+    }
+
+    pub fn render<'a>(&'a self, message: &'a BroadcastMessage) -> RenderedMessage<'a> {
+        RenderedMessage { message, master: self }
+    }
+
+    // JSON 输出补充 symbol 和显示价格
+    pub fn annotate_json(&self, message: &BroadcastMessage, value: &mut serde_json::Value) {
+        let (product_id, price) = match message {
+            BroadcastMessage::Trade(result) => (result.product_id, Some(result.price)),
+            BroadcastMessage::Status(stats) => (stats.product_id, None),
+        };
+        if let Some(info) = self.by_id(product_id) {
+            value["symbol"] = info.symbol.clone().into();
+            if let Some(price) = price {
+                value["display_price"] = info.format_price(price).into();
+            }
+        }
+    }
+}
+
+// 按产品主数据显示代码和价格，未知产品按原始格式显示
+pub struct RenderedMessage<'a> {
+    message: &'a BroadcastMessage,
+    master: &'a ProductMaster,
+}
+
+impl fmt::Display for RenderedMessage<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.message {
+            BroadcastMessage::Trade(result) => match self.master.by_id(result.product_id) {
+                Some(info) => write!(f, "🔥 TRADE: Product={}({}) | Price={} | Qty={} | BuyID={} | SellId={}| Net={}ns | Match={}ns",
+                    info.symbol, result.product_id, info.format_price(result.price), result.quantity,
+                    result.buy_order_id, result.sell_order_id, result.trade_network_time, result.internal_match_time)?,
+                None => return write!(f, "{}", self.message),
+            },
+            BroadcastMessage::Status(stats) => match self.master.by_id(stats.product_id) {
+                Some(info) => write!(f, "📊 STATUS: Product={}({}) | BidSize={} | AskSize={} | Matched={} | Received={}",
+                    info.symbol, stats.product_id, stats.bids_size, stats.ask_size, stats.matched_orders, stats.total_received_orders)?,
+                None => return write!(f, "{}", self.message),
+            },
+        }
+        write!(f, " | Inst={}", format_instance_tag(self.message.instance_tag()))?;
+        if self.message.sequence() != SEQUENCE_NONE {
+            write!(f, " | Seq={}", self.message.sequence())?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn product(price_decimals: u32, tick_size: Option<f64>, lot_size: u32) -> ProductInfo {
+        ProductInfo { product_id: 7, symbol: "ABC".to_string(), price_decimals, tick_size, lot_size }
+    }
+
+    #[test]
+    fn scales_whole_and_fractional_parts() {
+        let abc = product(3, None, 1);
+        assert_eq!(abc.parse_price("12.345"), Ok(12_345));
+        assert_eq!(abc.parse_price("12.3"), Ok(12_300));
+        assert_eq!(abc.parse_price("12"), Ok(12_000));
+        assert_eq!(abc.parse_price("0.005"), Ok(5));
+        assert_eq!(abc.parse_price(".5"), Ok(500));
+        assert_eq!(abc.format_price(12_345), "12.345");
+        assert_eq!(abc.format_price(5), "0.005");
+        assert_eq!(product(0, None, 1).parse_price("42"), Ok(42));
+    }
+
+    #[test]
+    fn rejects_too_many_decimals() {
+        let abc = product(2, None, 1);
+        assert_eq!(abc.parse_price("1.234"), Err("Price 1.234 has more than 2 decimals for ABC".to_string()));
+        assert!(product(0, None, 1).parse_price("1.5").is_err());
+    }
+
+    #[test]
+    fn rejects_malformed_prices() {
+        let abc = product(2, None, 1);
+        for text in ["", ".", "abc", "1.x", "-1", "+1", "1.2.3", "1.-5", "184467440737095516.16"] {
+            assert!(abc.parse_price(text).is_err(), "{} should be rejected", text);
+        }
+    }
+
+    #[test]
+    fn largest_scale_does_not_overflow() {
+        let fine = product(MAX_PRICE_DECIMALS, None, 1);
+        assert_eq!(fine.parse_price("1"), Ok(10_000_000_000_000_000_000));
+        assert_eq!(fine.format_price(15), "0.0000000000000000015");
+    }
+
+    #[test]
+    fn tick_and_lot_validation() {
+        let abc = product(3, Some(0.005), 100);
+        assert_eq!(abc.tick_units(), Some(5));
+        assert!(abc.validate(12_345, 200).is_ok());
+        assert_eq!(abc.validate(12_346, 200), Err("Price 12.346 is not a multiple of tick size 0.005 for ABC".to_string()));
+        assert_eq!(abc.validate(12_345, 150), Err("Quantity 150 is not a multiple of lot size 100 for ABC".to_string()));
+        // 没有最小变动价位时只检查整手
+        assert!(product(3, None, 1).validate(12_346, 1).is_ok());
+    }
+
+    #[test]
+    fn load_rejects_excessive_decimals() {
+        let path = std::env::temp_dir().join(format!("trading-client-products-{}.toml", std::process::id()));
+        fs::write(&path, "[[products]]\nproduct_id = 7\nsymbol = \"ABC\"\nprice_decimals = 20\n").unwrap();
+        let error = ProductMaster::load(&path).unwrap_err();
+        fs::remove_file(&path).unwrap();
+        assert!(error.ends_with("price_decimals 20 for ABC exceeds 19"), "{}", error);
+    }
+}
@@ -0,0 +1,273 @@
+// src/recovery.rs
+
+use std::collections::{BTreeMap, VecDeque};
+use std::time::{Duration, Instant};
+
+use crate::encoding::serialize_retransmit_request;
+use crate::listener::TaggedMessage;
+use crate::sequence::{forward_distance, next_sequence, SEQUENCE_WINDOW};
+use crate::transport::{Endpoint, FrameSender};
+use crate::types::{format_instance_tag, RetransmitRequest, SEQUENCE_NONE};
+
+// 每个实例最多缓存的帧数 (含空洞)，超过后放弃恢复
+const MAX_BUFFERED_FRAMES: usize = 8192;
+
+#[derive(Debug, Default)]
+struct InstanceBuffer {
+    next_deliver: u16,                         // 下一个应交付的序列号
+    slots: VecDeque<Option<TaggedMessage>>,    // slots[i] 对应 next_deliver + i，None 为缺口
+    gap_since: Option<Instant>,                // 最早未恢复缺口出现的时间
+}
+
+impl InstanceBuffer {
+    // 把队首连续的帧交付出去
+    fn drain_ready(&mut self, out: &mut Vec<TaggedMessage>) {
+        let mut progressed = false;
+        while let Some(Some(_)) = self.slots.front() {
+            if let Some(Some(message)) = self.slots.pop_front() {
+                out.push(message);
+            }
+            self.next_deliver = next_sequence(self.next_deliver);
+            progressed = true;
+        }
+        if self.slots.is_empty() {
+            self.gap_since = None;
+        } else if progressed {
+            self.gap_since = Some(Instant::now());
+        }
+    }
+
+    // 放弃所有缺口，按顺序交付已缓存的帧，返回放弃的帧数
+    fn skip_gaps(&mut self, out: &mut Vec<TaggedMessage>) -> u64 {
+        let mut skipped = 0;
+        while let Some(slot) = self.slots.pop_front() {
+            match slot {
+                Some(message) => out.push(message),
+                None => skipped += 1,
+            }
+            self.next_deliver = next_sequence(self.next_deliver);
+        }
+        self.gap_since = None;
+        skipped
+    }
+}
+
+// 重传恢复缓冲：发现缺口后发送 MSG_RETRANSMIT_REQUEST，
+// 并把重传回来的帧按序列号重新插入实时流，再交给下游
+pub struct RecoveryBuffer {
+    channel: FrameSender,
+    endpoint: Endpoint,
+    timeout: Duration,
+    instances: BTreeMap<[u8; 8], InstanceBuffer>,
+    requests_sent: u64,
+    recovered: u64,
+    unrecovered: u64,
+}
+
+impl RecoveryBuffer {
+    pub fn new(channel: FrameSender, endpoint: Endpoint, timeout: Duration) -> Self {
+        RecoveryBuffer {
+            channel,
+            endpoint,
+            timeout,
+            instances: BTreeMap::new(),
+            requests_sent: 0,
+            recovered: 0,
+            unrecovered: 0,
+        }
+    }
+
+    // 请求重传 start..=end
+    pub fn request(&mut self, instance_tag: &[u8; 8], start_sequence: u16, end_sequence: u16) -> Result<(), String> {
+        let request = RetransmitRequest { instance_tag: *instance_tag, start_sequence, end_sequence };
+        self.channel.send_frame(&serialize_retransmit_request(&request))?;
+        self.requests_sent += 1;
+        println!("↩️  RETRANSMIT REQUEST (Sent to {}): Instance={} | Seq={}..={}",
+            self.endpoint, format_instance_tag(instance_tag), start_sequence, end_sequence);
+        Ok(())
+    }
+
+    // 接收一条消息，把可以按序交付的消息追加到 out
+    pub fn accept(&mut self, tagged: TaggedMessage, out: &mut Vec<TaggedMessage>) {
+        let sequence = tagged.message.sequence();
+        if sequence == SEQUENCE_NONE {
+            out.push(tagged);
+            return;
+        }
+
+        let instance = self.instances.entry(*tagged.message.instance_tag()).or_default();
+        if instance.next_deliver == SEQUENCE_NONE {
+            instance.next_deliver = next_sequence(sequence);
+            out.push(tagged);
+            return;
+        }
+
+        let distance = forward_distance(instance.next_deliver, sequence);
+        if distance >= SEQUENCE_WINDOW {
+            // 已交付或已放弃的序列号：重复帧，丢弃
+            return;
+        }
+
+        let index = distance as usize;
+        if index >= MAX_BUFFERED_FRAMES {
+            // 缺口太大，放弃恢复，从当前帧重新开始
+            let buffered = instance.slots.len() as u64;
+            let skipped = instance.skip_gaps(out) + u64::from(distance) - buffered;
+            self.unrecovered += skipped;
+            eprintln!("⚠️  RECOVERY ABANDONED: Instance={} | Buffer limit exceeded | Skipped={}",
+                format_instance_tag(tagged.message.instance_tag()), skipped);
+            instance.next_deliver = next_sequence(sequence);
+            out.push(tagged);
+            return;
+        }
+
+        if index < instance.slots.len() {
+            if instance.slots[index].is_some() {
+                return; // 重复帧
+            }
+            self.recovered += 1; // 填补了缺口
+        } else {
+            instance.slots.resize(index + 1, None);
+        }
+        instance.slots[index] = Some(tagged);
+
+        if index > 0 && instance.gap_since.is_none() {
+            instance.gap_since = Some(Instant::now());
+        }
+        instance.drain_ready(out);
+    }
+
+    // 超时未恢复的缺口：放弃并交付缓存的帧
+    pub fn expire(&mut self, out: &mut Vec<TaggedMessage>) {
+        for (tag, instance) in self.instances.iter_mut() {
+            let expired = instance.gap_since.is_some_and(|since| since.elapsed() >= self.timeout);
+            if expired {
+                let skipped = instance.skip_gaps(out);
+                self.unrecovered += skipped;
+                eprintln!("⚠️  RECOVERY TIMEOUT: Instance={} | Unrecovered={}", format_instance_tag(tag), skipped);
+            }
+        }
+    }
+
+    // 会话结束时的恢复报告
+    pub fn print(&self) {
+        println!("Recovery ({}): Requests={} | Recovered={} | Unrecovered={}",
+            self.endpoint, self.requests_sent, self.recovered, self.unrecovered);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::UdpSocket;
+
+    use crate::encoding::BroadcastMessage;
+    use crate::transport::Scheme;
+    use crate::types::{MatchResult, MESSAGE_TOTAL_SIZE};
+
+    const TAG: [u8; 8] = *b"ENGINE-A";
+
+    fn trade(sequence: u16) -> TaggedMessage {
+        TaggedMessage {
+            group: 0,
+            src: "127.0.0.1:5001".parse().unwrap(),
+            message: BroadcastMessage::Trade(MatchResult {
+                instance_tag: TAG,
+                product_id: 1,
+                buy_order_id: u64::from(sequence),
+                sell_order_id: 0,
+                price: 100,
+                quantity: 1,
+                trade_network_time: 0,
+                internal_match_time: 0,
+                sequence,
+            }),
+        }
+    }
+
+    // 重传请求发往本机的一个 UDP Socket，测试中可以读回
+    fn buffer(timeout: Duration) -> (RecoveryBuffer, UdpSocket) {
+        let replay = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let target = replay.local_addr().unwrap();
+        let channel = FrameSender::Udp { socket: UdpSocket::bind("127.0.0.1:0").unwrap(), target };
+        let endpoint = Endpoint { scheme: Scheme::Udp, addr: target };
+        (RecoveryBuffer::new(channel, endpoint, timeout), replay)
+    }
+
+    fn sequences(out: &[TaggedMessage]) -> Vec<u16> {
+        out.iter().map(|tagged| tagged.message.sequence()).collect()
+    }
+
+    #[test]
+    fn retransmitted_frames_are_delivered_in_order() {
+        let (mut recovery, _replay) = buffer(Duration::from_secs(60));
+        let mut out = Vec::new();
+        for sequence in [1, 2, 5, 6] {
+            recovery.accept(trade(sequence), &mut out);
+        }
+        // 3、4 缺失，5、6 留在缓冲中
+        assert_eq!(sequences(&out), [1, 2]);
+
+        recovery.accept(trade(4), &mut out);
+        assert_eq!(sequences(&out), [1, 2]);
+        recovery.accept(trade(3), &mut out);
+        assert_eq!(sequences(&out), [1, 2, 3, 4, 5, 6]);
+        assert_eq!(recovery.recovered, 2);
+        assert_eq!(recovery.unrecovered, 0);
+
+        // 重传的重复帧不再交付
+        recovery.accept(trade(4), &mut out);
+        recovery.accept(trade(7), &mut out);
+        assert_eq!(sequences(&out), [1, 2, 3, 4, 5, 6, 7]);
+    }
+
+    #[test]
+    fn reorders_across_wraparound() {
+        let (mut recovery, _replay) = buffer(Duration::from_secs(60));
+        let mut out = Vec::new();
+        for sequence in [65534, 1, 2, 65535] {
+            recovery.accept(trade(sequence), &mut out);
+        }
+        assert_eq!(sequences(&out), [65534, 65535, 1, 2]);
+    }
+
+    #[test]
+    fn expired_gap_is_skipped() {
+        let (mut recovery, _replay) = buffer(Duration::ZERO);
+        let mut out = Vec::new();
+        for sequence in [1, 4, 5] {
+            recovery.accept(trade(sequence), &mut out);
+        }
+        recovery.expire(&mut out);
+        assert_eq!(sequences(&out), [1, 4, 5]);
+        assert_eq!(recovery.unrecovered, 2);
+
+        // 放弃后迟到的帧视为重复
+        recovery.accept(trade(3), &mut out);
+        recovery.accept(trade(6), &mut out);
+        assert_eq!(sequences(&out), [1, 4, 5, 6]);
+    }
+
+    #[test]
+    fn frames_without_sequence_pass_through() {
+        let (mut recovery, _replay) = buffer(Duration::from_secs(60));
+        let mut out = Vec::new();
+        recovery.accept(trade(1), &mut out);
+        recovery.accept(trade(3), &mut out);
+        recovery.accept(trade(SEQUENCE_NONE), &mut out);
+        assert_eq!(sequences(&out), [1, SEQUENCE_NONE]);
+    }
+
+    #[test]
+    fn request_sends_retransmit_frame() {
+        let (mut recovery, replay) = buffer(Duration::from_secs(60));
+        recovery.request(&TAG, 3, 4).unwrap();
+
+        let mut frame = [0u8; MESSAGE_TOTAL_SIZE];
+        let (len, _) = replay.recv_from(&mut frame).unwrap();
+        assert_eq!(len, MESSAGE_TOTAL_SIZE);
+        let expected = RetransmitRequest { instance_tag: TAG, start_sequence: 3, end_sequence: 4 };
+        assert_eq!(frame, serialize_retransmit_request(&expected));
+        assert_eq!(recovery.requests_sent, 1);
+    }
+}
@@ -0,0 +1,577 @@
+// src/regression.rs
+
+use std::fmt;
+use std::fs;
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant};
+
+use serde::Deserialize;
+
+use crate::encoding::BroadcastMessage;
+use crate::feed::FeedHandler;
+use crate::gateway::OrderGateway;
+use crate::listener::GroupListener;
+use crate::params::{parse_order_type, parse_price_type};
+use crate::types::{BroadcastStats, MatchResult, Order, ORDER_PRICE_TYPE_LIMIT};
+
+// --- 引擎回归测试场景 (YAML) ---
+//
+// name: engine-regression
+// timeout_ms: 2000            # 每个用例等待期望广播的默认超时
+// cases:
+//   - name: simple cross
+//     orders:
+//       - { order_id: 1001, product_id: 1, side: buy, price: 100, quantity: 10 }
+//       - { order_id: 1002, product_id: 1, side: sell, price: 100, quantity: 10 }
+//     cancels: [1003]
+//     expect_trades:
+//       - { buy_order_id: 1001, sell_order_id: 1002, price: 100, quantity: 10 }
+//     expect_stats:
+//       - { product_id: 1, matched_orders: 2 }
+//
+// 每个用例先发出订单，再发出撤单，然后等待所有期望出现；期望中省略的字段不比较。
+// 匹配规则：
+//   - 发送前先丢弃上一个用例迟到的广播，直到行情安静下来
+//   - 用例发出了订单时，只有涉及这些订单 ID 的成交才参与匹配
+//   - 每条成交或统计广播最多满足一个期望 (按列出顺序的第一个未满足的期望)，
+//     同一广播需要出现两次时就列出两个期望
+
+const DEFAULT_CASE_TIMEOUT_MS: u64 = 2000;
+// 等待期望时检查退出标志的间隔
+const TEST_POLL_INTERVAL: Duration = Duration::from_millis(50);
+// 用例开始前，连续这么久没有广播即视为安静
+const QUIET_PERIOD: Duration = Duration::from_millis(200);
+// 行情一直不安静 (例如引擎定时广播统计) 时最多等待这么久
+const MAX_DRAIN_TIME: Duration = Duration::from_secs(2);
+
+fn default_timeout_ms() -> u64 {
+    DEFAULT_CASE_TIMEOUT_MS
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Suite {
+    pub name: String,
+    #[serde(default = "default_timeout_ms")]
+    pub timeout_ms: u64,
+    pub cases: Vec<Case>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Case {
+    pub name: String,
+    #[serde(default)]
+    pub orders: Vec<OrderSpec>,
+    #[serde(default)]
+    pub cancels: Vec<u64>,
+    #[serde(default)]
+    pub expect_trades: Vec<ExpectedTrade>,
+    #[serde(default)]
+    pub expect_stats: Vec<ExpectedStats>,
+    pub timeout_ms: Option<u64>,
+}
+
+// 要发送的订单 (价格为线上单位，订单 ID 固定以便在期望中引用)
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct OrderSpec {
+    pub order_id: u64,
+    pub product_id: u16,
+    pub side: String,
+    #[serde(default)]
+    pub price: u64,
+    pub quantity: u32,
+    pub price_type: Option<String>,
+    #[serde(default)]
+    pub trigger_price: u64,
+}
+
+impl OrderSpec {
+    fn to_order(&self, submit_time: u64) -> Result<Order, String> {
+        Ok(Order {
+            product_id: self.product_id,
+            order_id: self.order_id,
+            price: self.price,
+            quantity: self.quantity,
+            order_type: parse_order_type(&self.side)?,
+            price_type: match &self.price_type {
+                Some(name) => parse_price_type(name)?,
+                None => ORDER_PRICE_TYPE_LIMIT,
+            },
+            submit_time,
+            expire_time: 0,
+            trigger_price: self.trigger_price,
+            display_quantity: 0,
+        })
+    }
+}
+
+// 期望的成交广播 (MatchResult)
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ExpectedTrade {
+    pub product_id: Option<u16>,
+    pub buy_order_id: Option<u64>,
+    pub sell_order_id: Option<u64>,
+    pub price: Option<u64>,
+    pub quantity: Option<u32>,
+}
+
+impl ExpectedTrade {
+    fn matches(&self, result: &MatchResult) -> bool {
+        self.product_id.is_none_or(|v| v == result.product_id)
+            && self.buy_order_id.is_none_or(|v| v == result.buy_order_id)
+            && self.sell_order_id.is_none_or(|v| v == result.sell_order_id)
+            && self.price.is_none_or(|v| v == result.price)
+            && self.quantity.is_none_or(|v| v == result.quantity)
+    }
+}
+
+// 期望的统计广播 (BroadcastStats) 计数
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ExpectedStats {
+    pub product_id: Option<u16>,
+    pub bids_size: Option<u32>,
+    pub ask_size: Option<u32>,
+    pub matched_orders: Option<u32>,
+    pub total_received_orders: Option<u32>,
+}
+
+impl ExpectedStats {
+    fn matches(&self, stats: &BroadcastStats) -> bool {
+        self.product_id.is_none_or(|v| v == stats.product_id)
+            && self.bids_size.is_none_or(|v| v == stats.bids_size)
+            && self.ask_size.is_none_or(|v| v == stats.ask_size)
+            && self.matched_orders.is_none_or(|v| v == stats.matched_orders)
+            && self.total_received_orders.is_none_or(|v| v == stats.total_received_orders)
+    }
+}
+
+// 只输出期望中指定的字段
+fn write_fields(f: &mut fmt::Formatter<'_>, kind: &str, fields: &[(&str, Option<u64>)]) -> fmt::Result {
+    write!(f, "{}", kind)?;
+    for (name, value) in fields {
+        if let Some(value) = value {
+            write!(f, " {}={}", name, value)?;
+        }
+    }
+    Ok(())
+}
+
+impl fmt::Display for ExpectedTrade {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write_fields(f, "trade", &[
+            ("product_id", self.product_id.map(u64::from)),
+            ("buy_order_id", self.buy_order_id),
+            ("sell_order_id", self.sell_order_id),
+            ("price", self.price),
+            ("quantity", self.quantity.map(u64::from)),
+        ])
+    }
+}
+
+impl fmt::Display for ExpectedStats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write_fields(f, "stats", &[
+            ("product_id", self.product_id.map(u64::from)),
+            ("bids_size", self.bids_size.map(u64::from)),
+            ("ask_size", self.ask_size.map(u64::from)),
+            ("matched_orders", self.matched_orders.map(u64::from)),
+            ("total_received_orders", self.total_received_orders.map(u64::from)),
+        ])
+    }
+}
+
+impl Case {
+    // 用例发出了订单时，只认涉及这些订单的成交
+    fn owns_trade(&self, result: &MatchResult) -> bool {
+        self.orders.is_empty() || self.orders.iter()
+            .any(|spec| spec.order_id == result.buy_order_id || spec.order_id == result.sell_order_id)
+    }
+}
+
+impl Suite {
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let text = fs::read_to_string(path)
+            .map_err(|e| format!("Failed to read test scenario {}: {}", path.display(), e))?;
+        let suite: Suite = serde_yaml::from_str(&text)
+            .map_err(|e| format!("Invalid test scenario {}: {}", path.display(), e))?;
+        // 发送前先检查所有订单字段
+        for case in &suite.cases {
+            for spec in &case.orders {
+                spec.to_order(0).map_err(|e| format!("Case '{}', order {}: {}", case.name, spec.order_id, e))?;
+            }
+        }
+        Ok(suite)
+    }
+}
+
+enum Outcome {
+    Passed,
+    Failed(String),  // 期望未在超时内出现
+    Error(String),   // 发送失败
+    Skipped,         // 被 Ctrl+C 中断
+}
+
+struct CaseResult<'a> {
+    name: &'a str,
+    elapsed: Duration,
+    outcome: Outcome,
+}
+
+// 标记第一个未满足且匹配的期望，返回它的描述
+fn claim<T: fmt::Display>(expected: &[T], seen: &mut [bool], matches: impl Fn(&T) -> bool) -> Option<String> {
+    let index = (0..expected.len()).find(|&i| !seen[i] && matches(&expected[i]))?;
+    seen[index] = true;
+    Some(expected[index].to_string())
+}
+
+// 一个用例中各期望的满足情况
+struct Expectations<'a> {
+    case: &'a Case,
+    trades_seen: Vec<bool>,
+    stats_seen: Vec<bool>,
+}
+
+impl<'a> Expectations<'a> {
+    fn new(case: &'a Case) -> Self {
+        Expectations {
+            case,
+            trades_seen: vec![false; case.expect_trades.len()],
+            stats_seen: vec![false; case.expect_stats.len()],
+        }
+    }
+
+    fn all_seen(&self) -> bool {
+        !self.trades_seen.contains(&false) && !self.stats_seen.contains(&false)
+    }
+
+    // 每条广播只满足一个期望，返回被满足的期望的描述
+    fn observe(&mut self, message: &BroadcastMessage) -> Option<String> {
+        let case = self.case;
+        match message {
+            BroadcastMessage::Trade(result) if case.owns_trade(result) =>
+                claim(&case.expect_trades, &mut self.trades_seen, |expected| expected.matches(result)),
+            BroadcastMessage::Trade(_) => None,
+            BroadcastMessage::Status(stats) =>
+                claim(&case.expect_stats, &mut self.stats_seen, |expected| expected.matches(stats)),
+        }
+    }
+
+    // 尚未满足的期望
+    fn missing(&self) -> Vec<String> {
+        let case = self.case;
+        case.expect_trades.iter().zip(&self.trades_seen)
+            .filter(|(_, seen)| !**seen)
+            .map(|(expected, _)| expected.to_string())
+            .chain(case.expect_stats.iter().zip(&self.stats_seen)
+                .filter(|(_, seen)| !**seen)
+                .map(|(expected, _)| expected.to_string()))
+            .collect()
+    }
+}
+
+// 接收并丢弃之前遗留的广播 (仍更新持仓)，返回丢弃的条数
+fn drain_feed(
+    listener: &mut GroupListener,
+    feed: &mut FeedHandler,
+    gateway: &mut OrderGateway,
+    running: &AtomicBool,
+) -> Result<usize, String> {
+    let started = Instant::now();
+    let mut quiet_since = started;
+    let mut delivered = Vec::new();
+    let mut fills = Vec::new();
+    let mut discarded = 0;
+    while running.load(Ordering::SeqCst) && quiet_since.elapsed() < QUIET_PERIOD && started.elapsed() < MAX_DRAIN_TIME {
+        feed.poll(listener, TEST_POLL_INTERVAL, &mut delivered)?;
+        if !delivered.is_empty() {
+            quiet_since = Instant::now();
+        }
+        for tagged in delivered.drain(..) {
+            gateway.observe(&tagged.message, &mut fills)?;
+            fills.clear();
+            discarded += 1;
+        }
+    }
+    Ok(discarded)
+}
+
+fn run_case(
+    case: &Case,
+    default_timeout_ms: u64,
+    listener: &mut GroupListener,
+    feed: &mut FeedHandler,
+    gateway: &mut OrderGateway,
+    running: &AtomicBool,
+) -> Result<Outcome, String> {
+    let discarded = drain_feed(listener, feed, gateway, running)?;
+    if discarded > 0 {
+        println!("  (discarded {} earlier broadcasts)", discarded);
+    }
+    if !running.load(Ordering::SeqCst) {
+        return Ok(Outcome::Skipped);
+    }
+
+    for spec in &case.orders {
+        let order = spec.to_order(gateway.now()?)?;
+        if let Err(e) = gateway.submit(&order) {
+            return Ok(Outcome::Error(format!("Failed to send order {}: {}", spec.order_id, e)));
+        }
+        println!("  ➡️  ORDER {} sent", spec.order_id);
+    }
+    for &order_id in &case.cancels {
+        if let Err(e) = gateway.cancel(order_id) {
+            return Ok(Outcome::Error(format!("Failed to send cancel {}: {}", order_id, e)));
+        }
+        println!("  ✖️  CANCEL {} sent", order_id);
+    }
+
+    let deadline = Instant::now() + Duration::from_millis(case.timeout_ms.unwrap_or(default_timeout_ms));
+    let mut expectations = Expectations::new(case);
+    let mut delivered = Vec::new();
+    let mut fills = Vec::new();
+
+    while !expectations.all_seen() {
+        let now = Instant::now();
+        if now >= deadline {
+            break;
+        }
+        if !running.load(Ordering::SeqCst) {
+            return Ok(Outcome::Skipped);
+        }
+        feed.poll(listener, (deadline - now).min(TEST_POLL_INTERVAL), &mut delivered)?;
+        for tagged in delivered.drain(..) {
+            gateway.observe(&tagged.message, &mut fills)?;
+            fills.clear();
+            if let Some(expected) = expectations.observe(&tagged.message) {
+                println!("  ✔ {}", expected);
+            }
+        }
+    }
+
+    let missing = expectations.missing();
+    if missing.is_empty() {
+        Ok(Outcome::Passed)
+    } else {
+        Ok(Outcome::Failed(format!("Not seen within timeout: {}", missing.join("; "))))
+    }
+}
+
+fn escape_xml(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&apos;")
+}
+
+fn junit_report(suite: &Suite, results: &[CaseResult], started: chrono::DateTime<chrono::Local>) -> String {
+    let count = |f: fn(&Outcome) -> bool| results.iter().filter(|r| f(&r.outcome)).count();
+    let failures = count(|o| matches!(o, Outcome::Failed(_)));
+    let errors = count(|o| matches!(o, Outcome::Error(_)));
+    let skipped = count(|o| matches!(o, Outcome::Skipped));
+    let total: f64 = results.iter().map(|r| r.elapsed.as_secs_f64()).sum();
+    let name = escape_xml(&suite.name);
+
+    let mut xml = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+    xml += &format!("<testsuites name=\"{}\" tests=\"{}\" failures=\"{}\" errors=\"{}\" skipped=\"{}\" time=\"{:.3}\">\n",
+        name, results.len(), failures, errors, skipped, total);
+    xml += &format!("  <testsuite name=\"{}\" tests=\"{}\" failures=\"{}\" errors=\"{}\" skipped=\"{}\" time=\"{:.3}\" timestamp=\"{}\">\n",
+        name, results.len(), failures, errors, skipped, total, started.format("%Y-%m-%dT%H:%M:%S"));
+    for result in results {
+        let open = format!("    <testcase classname=\"{}\" name=\"{}\" time=\"{:.3}\"", name, escape_xml(result.name), result.elapsed.as_secs_f64());
+        match &result.outcome {
+            Outcome::Passed => xml += &format!("{}/>\n", open),
+            Outcome::Failed(message) => xml += &format!("{}>\n      <failure type=\"ExpectationTimeout\" message=\"{}\"/>\n    </testcase>\n", open, escape_xml(message)),
+            Outcome::Error(message) => xml += &format!("{}>\n      <error type=\"SendError\" message=\"{}\"/>\n    </testcase>\n", open, escape_xml(message)),
+            Outcome::Skipped => xml += &format!("{}>\n      <skipped message=\"interrupted\"/>\n    </testcase>\n", open),
+        }
+    }
+    xml += "  </testsuite>\n</testsuites>\n";
+    xml
+}
+
+// 依次运行所有用例并写出 JUnit 报告；有失败时返回错误 (进程以非零状态退出)
+pub fn run(
+    suite: &Suite,
+    junit_path: &Path,
+    listener: &mut GroupListener,
+    feed: &mut FeedHandler,
+    gateway: &mut OrderGateway,
+    running: &AtomicBool,
+) -> Result<(), String> {
+    println!("\n=============================================");
+    println!("Test Suite: {} ({} cases)", suite.name, suite.cases.len());
+    println!("=============================================");
+
+    let started = chrono::Local::now();
+    let mut results = Vec::new();
+    for case in &suite.cases {
+        let start = Instant::now();
+        let outcome = if running.load(Ordering::SeqCst) {
+            println!("▶ {}", case.name);
+            run_case(case, suite.timeout_ms, listener, feed, gateway, running)?
+        } else {
+            Outcome::Skipped
+        };
+        let elapsed = start.elapsed();
+        match &outcome {
+            Outcome::Passed => println!("✅ PASS {} ({:.3}s)", case.name, elapsed.as_secs_f64()),
+            Outcome::Failed(message) => println!("❌ FAIL {}: {}", case.name, message),
+            Outcome::Error(message) => println!("❌ ERROR {}: {}", case.name, message),
+            Outcome::Skipped => println!("⏭️  SKIP {}", case.name),
+        }
+        results.push(CaseResult { name: &case.name, elapsed, outcome });
+    }
+
+    fs::write(junit_path, junit_report(suite, &results, started))
+        .map_err(|e| format!("Failed to write JUnit report {}: {}", junit_path.display(), e))?;
+    let passed = results.iter().filter(|r| matches!(r.outcome, Outcome::Passed)).count();
+    println!("Result: {}/{} passed | JUnit report: {}", passed, results.len(), junit_path.display());
+    if passed < results.len() {
+        return Err(format!("{} of {} test cases did not pass", results.len() - passed, results.len()));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn case(yaml: &str) -> Case {
+        serde_yaml::from_str(yaml).unwrap()
+    }
+
+    fn trade(buy_order_id: u64, sell_order_id: u64, quantity: u32) -> BroadcastMessage {
+        BroadcastMessage::Trade(MatchResult {
+            instance_tag: *b"ENGINE-A",
+            product_id: 1,
+            buy_order_id,
+            sell_order_id,
+            price: 100,
+            quantity,
+            trade_network_time: 0,
+            internal_match_time: 0,
+            sequence: 0,
+        })
+    }
+
+    fn stats(matched_orders: u32) -> BroadcastMessage {
+        BroadcastMessage::Status(BroadcastStats {
+            instance_tag: *b"ENGINE-A",
+            product_id: 1,
+            bids_size: 0,
+            ask_size: 0,
+            matched_orders,
+            total_received_orders: matched_orders,
+            start_time: 0,
+            sequence: 0,
+        })
+    }
+
+    #[test]
+    fn one_frame_satisfies_one_expectation() {
+        let case = case("
+name: twice
+orders:
+  - { order_id: 1, product_id: 1, side: buy, price: 100, quantity: 20 }
+  - { order_id: 2, product_id: 1, side: sell, price: 100, quantity: 20 }
+expect_trades:
+  - { buy_order_id: 1, sell_order_id: 2, quantity: 10 }
+  - { buy_order_id: 1, sell_order_id: 2, quantity: 10 }
+expect_stats:
+  - { matched_orders: 2 }
+  - { matched_orders: 2 }
+");
+        let mut expectations = Expectations::new(&case);
+        assert!(expectations.observe(&trade(1, 2, 10)).is_some());
+        assert!(expectations.observe(&stats(2)).is_some());
+        // 同一帧不能同时满足两个相同的期望
+        assert_eq!(expectations.missing().len(), 2);
+        assert!(!expectations.all_seen());
+
+        assert!(expectations.observe(&trade(1, 2, 10)).is_some());
+        assert!(expectations.observe(&stats(2)).is_some());
+        assert!(expectations.all_seen());
+        // 全部满足后多余的帧不再认领
+        assert_eq!(expectations.observe(&trade(1, 2, 10)), None);
+    }
+
+    #[test]
+    fn leftover_trade_does_not_satisfy_next_case() {
+        let first = case("
+name: first
+orders:
+  - { order_id: 1001, product_id: 1, side: buy, price: 100, quantity: 10 }
+  - { order_id: 1002, product_id: 1, side: sell, price: 100, quantity: 10 }
+expect_trades:
+  - { quantity: 10 }
+");
+        let second = case("
+name: second
+orders:
+  - { order_id: 2001, product_id: 1, side: buy, price: 100, quantity: 10 }
+  - { order_id: 2002, product_id: 1, side: sell, price: 100, quantity: 10 }
+expect_trades:
+  - { quantity: 10 }
+");
+        let mut expectations = Expectations::new(&first);
+        assert!(expectations.observe(&trade(1001, 1002, 10)).is_some());
+        assert!(expectations.all_seen());
+
+        // 上一个用例的成交迟到：不涉及本用例的订单，不参与匹配
+        let mut expectations = Expectations::new(&second);
+        assert_eq!(expectations.observe(&trade(1001, 1002, 10)), None);
+        assert_eq!(expectations.missing(), vec!["trade quantity=10".to_string()]);
+        assert_eq!(expectations.observe(&trade(2001, 2002, 10)), Some("trade quantity=10".to_string()));
+        assert!(expectations.all_seen());
+    }
+
+    #[test]
+    fn case_without_orders_accepts_any_trade() {
+        let case = case("
+name: passive
+expect_trades:
+  - { price: 100 }
+");
+        let mut expectations = Expectations::new(&case);
+        assert_eq!(expectations.observe(&trade(7, 8, 1)), Some("trade price=100".to_string()));
+    }
+
+    #[test]
+    fn escapes_xml_special_characters() {
+        assert_eq!(escape_xml("a<b> & \"c\" 'd'"), "a&lt;b&gt; &amp; &quot;c&quot; &apos;d&apos;");
+        assert_eq!(escape_xml("&amp;"), "&amp;amp;");
+    }
+
+    #[test]
+    fn junit_report_counts_and_escapes() {
+        let suite: Suite = serde_yaml::from_str("
+name: suite <A&B>
+cases: []
+").unwrap();
+        let results = [
+            CaseResult { name: "cross \"buy\"", elapsed: Duration::from_millis(1500), outcome: Outcome::Passed },
+            CaseResult { name: "late", elapsed: Duration::from_millis(2000),
+                outcome: Outcome::Failed("Not seen within timeout: trade price=100 <x>".to_string()) },
+            CaseResult { name: "send", elapsed: Duration::ZERO, outcome: Outcome::Error("Failed to send order 1: a & b".to_string()) },
+            CaseResult { name: "stopped", elapsed: Duration::ZERO, outcome: Outcome::Skipped },
+        ];
+        let started = chrono::Local.with_ymd_and_hms(2025, 10, 18, 9, 30, 0).unwrap();
+        let xml = junit_report(&suite, &results, started);
+
+        assert!(xml.starts_with("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n"));
+        assert!(xml.contains("<testsuites name=\"suite &lt;A&amp;B&gt;\" tests=\"4\" failures=\"1\" errors=\"1\" skipped=\"1\" time=\"3.500\">"));
+        assert!(xml.contains("timestamp=\"2025-10-18T09:30:00\""));
+        assert!(xml.contains("<testcase classname=\"suite &lt;A&amp;B&gt;\" name=\"cross &quot;buy&quot;\" time=\"1.500\"/>"));
+        assert!(xml.contains("<failure type=\"ExpectationTimeout\" message=\"Not seen within timeout: trade price=100 &lt;x&gt;\"/>"));
+        assert!(xml.contains("<error type=\"SendError\" message=\"Failed to send order 1: a &amp; b\"/>"));
+        assert!(xml.contains("<skipped message=\"interrupted\"/>"));
+        assert!(xml.ends_with("  </testsuite>\n</testsuites>\n"));
+    }
+}
@@ -0,0 +1,360 @@
+// src/risk.rs
+
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use crate::encoding::BroadcastMessage;
+use crate::types::Order;
+
+const NANOS_PER_SECOND: u64 = 1_000_000_000;
+
+// --- 风控配置 (TOML) ---
+//
+// max_open_orders = 50
+// max_orders_per_second = 10
+// allowed_products = [7, 12]
+// price_collar_percent = 5.0
+//
+// [[products]]
+// product_id = 7
+// max_quantity = 1000
+// max_notional = 2000000
+// price_collar_percent = 2.0
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RiskConfig {
+    pub max_open_orders: Option<usize>,
+    pub max_orders_per_second: Option<usize>,
+    #[serde(default)]
+    pub allowed_products: Vec<u16>,       // 为空表示不限制
+    pub price_collar_percent: Option<f64>, // 所有产品的默认价格偏离上限
+    #[serde(default)]
+    pub products: Vec<ProductLimits>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ProductLimits {
+    pub product_id: u16,
+    pub max_quantity: Option<u32>,
+    pub max_notional: Option<u64>,          // price * quantity
+    pub price_collar_percent: Option<f64>,  // 相对最近成交价的最大偏离 (%)
+}
+
+impl RiskConfig {
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let text = fs::read_to_string(path)
+            .map_err(|e| format!("Failed to read risk config {}: {}", path.display(), e))?;
+        toml::from_str(&text)
+            .map_err(|e| format!("Invalid risk config {}: {}", path.display(), e))
+    }
+
+    fn limits(&self, product_id: u16) -> Option<&ProductLimits> {
+        self.products.iter().find(|limits| limits.product_id == product_id)
+    }
+}
+
+// --- 风控状态：跨进程持久化，每次运行只发一单时也能统计挂单和频率 ---
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct LastTrade {
+    product_id: u16,
+    price: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct OpenOrder {
+    order_id: u64,
+    product_id: u16,
+    remaining: u32,
+    expire_time: u64, // 0 表示 GTC
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct RiskState {
+    #[serde(default)]
+    recent_sends: Vec<u64>,        // 最近一秒内的发送时间 (纳秒)
+    #[serde(default)]
+    open_orders: Vec<OpenOrder>,
+    #[serde(default)]
+    last_trades: Vec<LastTrade>,   // 行情中看到的最近成交价
+}
+
+// 违反的风控规则
+#[derive(Debug, Clone)]
+pub struct RiskViolation {
+    pub rule: &'static str,
+    pub detail: String,
+}
+
+impl fmt::Display for RiskViolation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Risk check failed [{}]: {}", self.rule, self.detail)
+    }
+}
+
+fn violation(rule: &'static str, detail: String) -> Result<(), RiskViolation> {
+    Err(RiskViolation { rule, detail })
+}
+
+// 下单前风控，发送前由 OrderGateway 调用
+pub struct RiskEngine {
+    config: RiskConfig,
+    state: RiskState,
+    state_path: PathBuf,
+}
+
+impl RiskEngine {
+    pub fn load(config: RiskConfig, state_path: PathBuf) -> Result<Self, String> {
+        let state = match fs::read_to_string(&state_path) {
+            Ok(text) => toml::from_str(&text)
+                .map_err(|e| format!("Invalid risk state {}: {}", state_path.display(), e))?,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => RiskState::default(),
+            Err(e) => return Err(format!("Failed to read risk state {}: {}", state_path.display(), e)),
+        };
+        Ok(RiskEngine { config, state, state_path })
+    }
+
+    pub fn save(&self) -> Result<(), String> {
+        if let Some(dir) = self.state_path.parent() {
+            fs::create_dir_all(dir)
+                .map_err(|e| format!("Failed to create state directory {}: {}", dir.display(), e))?;
+        }
+        let text = toml::to_string(&self.state)
+            .map_err(|e| format!("Failed to serialize risk state: {}", e))?;
+        fs::write(&self.state_path, text)
+            .map_err(|e| format!("Failed to write risk state {}: {}", self.state_path.display(), e))
+    }
+
+    pub fn last_trade_price(&self, product_id: u16) -> Option<u64> {
+        self.state.last_trades.iter()
+            .find(|trade| trade.product_id == product_id)
+            .map(|trade| trade.price)
+    }
+
+    // 清理过期的发送记录和挂单
+    fn prune(&mut self, now: u64) {
+        self.state.recent_sends.retain(|&sent| now.saturating_sub(sent) < NANOS_PER_SECOND);
+        self.state.open_orders.retain(|order| order.expire_time == 0 || order.expire_time > now);
+    }
+
+    pub fn check(&mut self, order: &Order, now: u64) -> Result<(), RiskViolation> {
+        self.prune(now);
+        let config = &self.config;
+
+        if !config.allowed_products.is_empty() && !config.allowed_products.contains(&order.product_id) {
+            return violation("allowed_products",
+                format!("product {} is not in the allow-list {:?}", order.product_id, config.allowed_products));
+        }
+
+        if let Some(max) = config.max_orders_per_second
+            && self.state.recent_sends.len() >= max {
+            return violation("max_orders_per_second",
+                format!("{} orders sent in the last second, limit is {}", self.state.recent_sends.len(), max));
+        }
+
+        if let Some(max) = config.max_open_orders
+            && self.state.open_orders.len() >= max {
+            return violation("max_open_orders",
+                format!("{} orders already open, limit is {}", self.state.open_orders.len(), max));
+        }
+
+        let limits = config.limits(order.product_id);
+        let last_price = self.last_trade_price(order.product_id);
+
+        if let Some(max) = limits.and_then(|l| l.max_quantity)
+            && order.quantity > max {
+            return violation("max_quantity",
+                format!("quantity {} exceeds limit {} for product {}", order.quantity, max, order.product_id));
+        }
+
+        // 市价单 (含止损市价单) 按最近成交价估算名义金额
+        let is_market = !order.has_limit_price();
+        let notional_price = if is_market { last_price } else { Some(order.price) };
+        if let (Some(max), Some(price)) = (limits.and_then(|l| l.max_notional), notional_price) {
+            let notional = u128::from(price) * u128::from(order.quantity);
+            if notional > u128::from(max) {
+                return violation("max_notional",
+                    format!("notional {} exceeds limit {} for product {}", notional, max, order.product_id));
+            }
+        }
+
+        // 价格偏离：限价单与最近成交价比较，没有成交价时不检查
+        let collar = limits.and_then(|l| l.price_collar_percent).or(config.price_collar_percent);
+        if let (Some(collar), Some(last), false) = (collar, last_price, is_market)
+            && last > 0 {
+            let deviation = (order.price as f64 - last as f64).abs() / last as f64 * 100.0;
+            if deviation > collar {
+                return violation("price_collar",
+                    format!("price {} deviates {:.2}% from last trade {} (limit {}%)", order.price, deviation, last, collar));
+            }
+        }
+
+        Ok(())
+    }
+
+    pub fn record_submit(&mut self, order: &Order, now: u64) {
+        self.state.recent_sends.push(now);
+        // 市价单和 IOC/FOK 立即成交或撤销，不计入挂单
+        if !order.is_market() && !order.is_immediate() {
+            self.state.open_orders.push(OpenOrder {
+                order_id: order.order_id,
+                product_id: order.product_id,
+                remaining: order.quantity,
+                expire_time: order.expire_time,
+            });
+        }
+    }
+
+    pub fn record_cancel(&mut self, order_id: u64, now: u64) {
+        self.state.recent_sends.push(now);
+        self.state.open_orders.retain(|order| order.order_id != order_id);
+    }
+
+    // 行情更新：最近成交价，以及自己挂单的成交
+    pub fn observe(&mut self, message: &BroadcastMessage) {
+        let BroadcastMessage::Trade(result) = message else {
+            return;
+        };
+
+        match self.state.last_trades.iter_mut().find(|trade| trade.product_id == result.product_id) {
+            Some(trade) => trade.price = result.price,
+            None => self.state.last_trades.push(LastTrade { product_id: result.product_id, price: result.price }),
+        }
+
+        for order in self.state.open_orders.iter_mut() {
+            if order.order_id == result.buy_order_id || order.order_id == result.sell_order_id {
+                order.remaining = order.remaining.saturating_sub(result.quantity);
+            }
+        }
+        self.state.open_orders.retain(|order| order.remaining > 0);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{MatchResult, ORDER_PRICE_TYPE_LIMIT, ORDER_PRICE_TYPE_MARKET, ORDER_TIF_IOC, ORDER_TYPE_BUY};
+
+    const NOW: u64 = 1_700_000_000 * NANOS_PER_SECOND;
+
+    fn engine(config: RiskConfig) -> RiskEngine {
+        RiskEngine { config, state: RiskState::default(), state_path: PathBuf::from("unused-risk-state.toml") }
+    }
+
+    fn order(order_id: u64, price: u64, quantity: u32) -> Order {
+        Order {
+            product_id: 7,
+            order_id,
+            price,
+            quantity,
+            order_type: ORDER_TYPE_BUY,
+            price_type: ORDER_PRICE_TYPE_LIMIT,
+            submit_time: NOW,
+            expire_time: 0,
+            trigger_price: 0,
+            display_quantity: 0,
+        }
+    }
+
+    fn trade(buy_order_id: u64, price: u64, quantity: u32) -> BroadcastMessage {
+        BroadcastMessage::Trade(MatchResult {
+            instance_tag: [0; 8],
+            product_id: 7,
+            buy_order_id,
+            sell_order_id: 0,
+            price,
+            quantity,
+            trade_network_time: 0,
+            internal_match_time: 0,
+            sequence: 0,
+        })
+    }
+
+    fn limits(limits: ProductLimits) -> RiskConfig {
+        RiskConfig { products: vec![ProductLimits { product_id: 7, ..limits }], ..RiskConfig::default() }
+    }
+
+    fn rule(result: Result<(), RiskViolation>) -> &'static str {
+        result.expect_err("order should breach a limit").rule
+    }
+
+    #[test]
+    fn max_quantity_breach() {
+        let mut risk = engine(limits(ProductLimits { max_quantity: Some(100), ..ProductLimits::default() }));
+        assert!(risk.check(&order(1, 10, 100), NOW).is_ok());
+        assert_eq!(rule(risk.check(&order(1, 10, 101), NOW)), "max_quantity");
+    }
+
+    #[test]
+    fn max_notional_uses_last_trade_for_market_orders() {
+        let mut risk = engine(limits(ProductLimits { max_notional: Some(1_000), ..ProductLimits::default() }));
+        assert_eq!(rule(risk.check(&order(1, 101, 10), NOW)), "max_notional");
+
+        let mut market = order(2, 0, 10);
+        market.price_type = ORDER_PRICE_TYPE_MARKET;
+        // 没有成交价时无法估算，不检查
+        assert!(risk.check(&market, NOW).is_ok());
+        risk.observe(&trade(99, 200, 1));
+        assert_eq!(rule(risk.check(&market, NOW)), "max_notional");
+    }
+
+    #[test]
+    fn price_collar_against_last_trade() {
+        let mut risk = engine(RiskConfig { price_collar_percent: Some(5.0), ..RiskConfig::default() });
+        assert!(risk.check(&order(1, 1_000, 1), NOW).is_ok());
+        risk.observe(&trade(99, 100, 1));
+        assert!(risk.check(&order(1, 105, 1), NOW).is_ok());
+        assert_eq!(rule(risk.check(&order(1, 106, 1), NOW)), "price_collar");
+        assert_eq!(rule(risk.check(&order(1, 94, 1), NOW)), "price_collar");
+    }
+
+    #[test]
+    fn allowed_products() {
+        let mut risk = engine(RiskConfig { allowed_products: vec![8], ..RiskConfig::default() });
+        assert_eq!(rule(risk.check(&order(1, 10, 1), NOW)), "allowed_products");
+    }
+
+    #[test]
+    fn orders_per_second_window() {
+        let mut risk = engine(RiskConfig { max_orders_per_second: Some(2), ..RiskConfig::default() });
+        risk.record_submit(&order(1, 10, 1), NOW);
+        risk.record_cancel(1, NOW + 1);
+        assert_eq!(rule(risk.check(&order(2, 10, 1), NOW + 2)), "max_orders_per_second");
+        // 一秒后旧记录过期
+        assert!(risk.check(&order(2, 10, 1), NOW + NANOS_PER_SECOND).is_ok());
+    }
+
+    #[test]
+    fn open_orders_released_by_fill_cancel_and_expiry() {
+        let mut risk = engine(RiskConfig { max_open_orders: Some(1), ..RiskConfig::default() });
+        risk.record_submit(&order(1, 10, 5), NOW);
+        assert_eq!(rule(risk.check(&order(2, 10, 1), NOW)), "max_open_orders");
+
+        // 部分成交仍占用，全部成交后释放
+        risk.observe(&trade(1, 10, 3));
+        assert_eq!(rule(risk.check(&order(2, 10, 1), NOW)), "max_open_orders");
+        risk.observe(&trade(1, 10, 2));
+        assert!(risk.check(&order(2, 10, 1), NOW).is_ok());
+
+        risk.record_submit(&order(2, 10, 1), NOW);
+        risk.record_cancel(2, NOW);
+        assert!(risk.check(&order(3, 10, 1), NOW).is_ok());
+
+        let mut gtd = order(3, 10, 1);
+        gtd.expire_time = NOW + 10;
+        risk.record_submit(&gtd, NOW);
+        assert_eq!(rule(risk.check(&order(4, 10, 1), NOW)), "max_open_orders");
+        assert!(risk.check(&order(4, 10, 1), NOW + 10).is_ok());
+
+        // IOC 不挂单
+        let mut ioc = order(5, 10, 1);
+        ioc.price_type |= ORDER_TIF_IOC;
+        risk.record_submit(&ioc, NOW + 10);
+        assert!(risk.check(&order(6, 10, 1), NOW + 10).is_ok());
+    }
+}
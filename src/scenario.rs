@@ -0,0 +1,289 @@
+// src/scenario.rs
+
+use std::cell::RefCell;
+use std::collections::{HashMap, VecDeque};
+use std::fs;
+use std::path::Path;
+use std::rc::Rc;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant};
+
+use rhai::{Dynamic, Engine, EvalAltResult, FnPtr, Map, NativeCallContext};
+
+use crate::encoding::BroadcastMessage;
+use crate::feed::FeedHandler;
+use crate::gateway::OrderGateway;
+use crate::listener::GroupListener;
+use crate::params::{parse_order_type, parse_price_type};
+use crate::types::{Order, ORDER_PRICE_TYPE_LIMIT, ORDER_PRICE_TYPE_STOP, ORDER_PRICE_TYPE_STOP_LIMIT};
+use crate::types::{ORDER_TIF_FOK, ORDER_TIF_IOC};
+
+// --- 场景脚本 (Rhai) ---
+//
+// let id = submit(#{ product_id: 1, side: "buy", price: 100, quantity: 10 });
+// let trade = wait_for(|m| m.type == "trade" && m.buy_order_id == id, 2000);
+// assert(trade != (), "no fill for " + id);
+// if filled(id) < 10 { cancel(id); }
+// sleep(500);
+// assert_eq(trade_count(), 1, "trade count");
+//
+// submit 的可选字段：price_type (limit/market/stop/stop-limit)、tif (gtc/ioc/fok)、
+//   trigger_price、order_id (固定 ID 便于复现)
+// wait_for 的超时单位为毫秒，超时返回 ()；消息字段与 --output-format json 相同
+
+// wait_for 不指定超时时的默认值
+const DEFAULT_WAIT_TIMEOUT: Duration = Duration::from_secs(5);
+// 等待期间检查退出标志的间隔
+const SCENARIO_POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+// 脚本运行期间由脚本绑定共享的收发端，结束后交还给调用方做退出清理
+pub struct Session {
+    pub listener: GroupListener,
+    pub feed: FeedHandler,
+    pub gateway: OrderGateway,
+}
+
+struct ScenarioState {
+    session: Session,
+    running: Arc<AtomicBool>,
+    pending: VecDeque<Dynamic>, // 已接收、尚未被 wait_for 检查的消息
+    filled: HashMap<u64, u64>,  // 自己订单的累计成交数量
+    last_order_id: u64,
+    submitted: u64,
+    cancelled: u64,
+    trades: u64,
+    assertions: u64,
+}
+
+impl ScenarioState {
+    // 接收一批消息：更新持仓和成交，消息排队等待 wait_for
+    fn pump(&mut self, timeout: Duration) -> Result<(), String> {
+        if !self.running.load(Ordering::SeqCst) {
+            return Err("Scenario interrupted".to_string());
+        }
+        let mut delivered = Vec::new();
+        let mut fills = Vec::new();
+        let session = &mut self.session;
+        session.feed.poll(&mut session.listener, timeout.min(SCENARIO_POLL_INTERVAL), &mut delivered)?;
+
+        for tagged in delivered {
+            session.gateway.observe(&tagged.message, &mut fills)?;
+            if matches!(tagged.message, BroadcastMessage::Trade(_)) {
+                self.trades += 1;
+            }
+            for fill in fills.drain(..) {
+                println!("{}", fill);
+                *self.filled.entry(fill.order_id).or_default() += u64::from(fill.quantity);
+            }
+            let message = rhai::serde::to_dynamic(tagged.message.to_json())
+                .map_err(|e| format!("Failed to convert message for script: {}", e))?;
+            self.pending.push_back(message);
+        }
+        Ok(())
+    }
+
+    fn next_message(&mut self, deadline: Instant) -> Result<Option<Dynamic>, String> {
+        loop {
+            if let Some(message) = self.pending.pop_front() {
+                return Ok(Some(message));
+            }
+            let now = Instant::now();
+            if now >= deadline {
+                return Ok(None);
+            }
+            self.pump(deadline - now)?;
+        }
+    }
+
+    fn sleep(&mut self, duration: Duration) -> Result<(), String> {
+        let deadline = Instant::now() + duration;
+        while Instant::now() < deadline {
+            self.pump(deadline - Instant::now())?;
+        }
+        Ok(())
+    }
+
+    fn submit(&mut self, spec: &Map) -> Result<u64, String> {
+        let integer = |key: &str| -> Result<Option<u64>, String> {
+            match spec.get(key) {
+                None => Ok(None),
+                Some(value) => value.as_int()
+                    .ok()
+                    .and_then(|value| u64::try_from(value).ok())
+                    .map(Some)
+                    .ok_or_else(|| format!("submit: '{}' must be a non-negative integer", key)),
+            }
+        };
+        let text = |key: &str| -> Result<Option<String>, String> {
+            match spec.get(key) {
+                None => Ok(None),
+                Some(value) => value.clone().into_string()
+                    .map(Some)
+                    .map_err(|_| format!("submit: '{}' must be a string", key)),
+            }
+        };
+
+        let product_id = integer("product_id")?.ok_or("submit: 'product_id' is required")?;
+        let product_id = u16::try_from(product_id).map_err(|_| format!("submit: product_id out of range: {}", product_id))?;
+        let side = parse_order_type(&text("side")?.ok_or("submit: 'side' is required")?)?;
+        let quantity = integer("quantity")?.ok_or("submit: 'quantity' is required")?;
+        let quantity = u32::try_from(quantity).map_err(|_| format!("submit: quantity out of range: {}", quantity))?;
+        let price = integer("price")?.unwrap_or(0);
+        let price_type = match text("price_type")? {
+            Some(name) => parse_price_type(&name)?,
+            None => ORDER_PRICE_TYPE_LIMIT,
+        };
+        let trigger_price = integer("trigger_price")?.unwrap_or(0);
+        if matches!(price_type, ORDER_PRICE_TYPE_STOP | ORDER_PRICE_TYPE_STOP_LIMIT) && trigger_price == 0 {
+            return Err("submit: stop orders require 'trigger_price'".to_string());
+        }
+        let tif_flags = match text("tif")?.as_deref().map(str::to_lowercase).as_deref() {
+            None | Some("gtc") => 0,
+            Some("ioc") => ORDER_TIF_IOC,
+            Some("fok") => ORDER_TIF_FOK,
+            Some(other) => return Err(format!("submit: unsupported tif '{}' (gtc, ioc or fok)", other)),
+        };
+
+        // 订单 ID 默认取时钟的当前纳秒时间，并保证在脚本内严格递增
+        let submit_time = self.session.gateway.now()?;
+        let order_id = match integer("order_id")? {
+            Some(order_id) => order_id,
+            None => submit_time.max(self.last_order_id + 1),
+        };
+        self.last_order_id = self.last_order_id.max(order_id);
+
+        let order = Order {
+            product_id,
+            order_id,
+            price,
+            quantity,
+            order_type: side,
+            price_type: price_type | tif_flags,
+            submit_time,
+            expire_time: 0,
+            trigger_price,
+            display_quantity: 0,
+        };
+        self.session.gateway.submit(&order)?;
+        self.submitted += 1;
+        println!("➡️  SUBMIT: OrderID={} | Product={} | Type={} | Price={} | Qty={}",
+            order_id, product_id, order.price_type_name(), price, quantity);
+        Ok(order_id)
+    }
+
+    fn cancel(&mut self, order_id: u64) -> Result<(), String> {
+        self.session.gateway.cancel(order_id)?;
+        self.cancelled += 1;
+        println!("✖️  CANCEL: OrderID={}", order_id);
+        Ok(())
+    }
+}
+
+fn to_u64(value: i64, name: &str) -> Result<u64, Box<EvalAltResult>> {
+    u64::try_from(value).map_err(|_| format!("{} must be non-negative: {}", name, value).into())
+}
+
+fn wait_for(state: &RefCell<ScenarioState>, context: &NativeCallContext, predicate: &FnPtr, timeout: Duration) -> Result<Dynamic, Box<EvalAltResult>> {
+    let deadline = Instant::now() + timeout;
+    // 调用谓词前释放借用：谓词本身可以调用 submit/cancel
+    loop {
+        let next = state.borrow_mut().next_message(deadline)?;
+        let Some(message) = next else {
+            return Ok(Dynamic::UNIT);
+        };
+        if predicate.call_within_context::<bool>(context, (message.clone(),))? {
+            return Ok(message);
+        }
+    }
+}
+
+fn build_engine(state: &Rc<RefCell<ScenarioState>>) -> Engine {
+    let mut engine = Engine::new();
+
+    let s = state.clone();
+    engine.register_fn("submit", move |spec: Map| -> Result<i64, Box<EvalAltResult>> {
+        let order_id = s.borrow_mut().submit(&spec)?;
+        Ok(order_id as i64)
+    });
+    let s = state.clone();
+    engine.register_fn("cancel", move |order_id: i64| -> Result<(), Box<EvalAltResult>> {
+        Ok(s.borrow_mut().cancel(to_u64(order_id, "order_id")?)?)
+    });
+    let s = state.clone();
+    engine.register_fn("sleep", move |millis: i64| -> Result<(), Box<EvalAltResult>> {
+        Ok(s.borrow_mut().sleep(Duration::from_millis(to_u64(millis, "sleep")?))?)
+    });
+    let s = state.clone();
+    engine.register_fn("wait_for", move |context: NativeCallContext, predicate: FnPtr, millis: i64| {
+        wait_for(&s, &context, &predicate, Duration::from_millis(to_u64(millis, "timeout")?))
+    });
+    let s = state.clone();
+    engine.register_fn("wait_for", move |context: NativeCallContext, predicate: FnPtr| {
+        wait_for(&s, &context, &predicate, DEFAULT_WAIT_TIMEOUT)
+    });
+    let s = state.clone();
+    engine.register_fn("filled", move |order_id: i64| -> Result<i64, Box<EvalAltResult>> {
+        let order_id = to_u64(order_id, "order_id")?;
+        Ok(s.borrow().filled.get(&order_id).copied().unwrap_or(0) as i64)
+    });
+    let s = state.clone();
+    engine.register_fn("trade_count", move || s.borrow().trades as i64);
+
+    let s = state.clone();
+    engine.register_fn("assert", move |condition: bool, message: &str| -> Result<(), Box<EvalAltResult>> {
+        if !condition {
+            return Err(format!("Assertion failed: {}", message).into());
+        }
+        s.borrow_mut().assertions += 1;
+        Ok(())
+    });
+    let s = state.clone();
+    engine.register_fn("assert_eq", move |actual: Dynamic, expected: Dynamic, message: &str| -> Result<(), Box<EvalAltResult>> {
+        // 只比较同类型的值 (整数、字符串、布尔等)
+        let equal = actual.type_name() == expected.type_name() && actual.to_string() == expected.to_string();
+        if !equal {
+            return Err(format!("Assertion failed: {} (expected {}, got {})", message, expected, actual).into());
+        }
+        s.borrow_mut().assertions += 1;
+        Ok(())
+    });
+    engine
+}
+
+// 运行场景脚本；无论成功与否都交还收发端
+pub fn run(path: &Path, session: Session, running: Arc<AtomicBool>) -> (Session, Result<(), String>) {
+    let state = Rc::new(RefCell::new(ScenarioState {
+        session,
+        running,
+        pending: VecDeque::new(),
+        filled: HashMap::new(),
+        last_order_id: 0,
+        submitted: 0,
+        cancelled: 0,
+        trades: 0,
+        assertions: 0,
+    }));
+
+    let result = match path.extension().and_then(|ext| ext.to_str()) {
+        Some("rhai") => fs::read_to_string(path)
+            .map_err(|e| format!("Failed to read scenario {}: {}", path.display(), e))
+            .and_then(|script| {
+                println!("\n=============================================");
+                println!("Running Scenario: {}", path.display());
+                println!("=============================================");
+                build_engine(&state).run(&script)
+                    .map_err(|e| format!("Scenario {} failed: {}", path.display(), e))
+            }),
+        Some("lua") => Err("Lua scenarios are not supported by this build, use a .rhai script".to_string()),
+        _ => Err(format!("Unknown scenario type {} (expected .rhai)", path.display())),
+    };
+
+    // 引擎已释放，脚本绑定持有的引用全部归还
+    let state = Rc::into_inner(state)
+        .expect("scenario engine released")
+        .into_inner();
+    println!("Scenario: {} | Submitted: {} | Cancelled: {} | Trades Seen: {} | Assertions Passed: {}",
+        if result.is_ok() { "PASSED" } else { "FAILED" }, state.submitted, state.cancelled, state.trades, state.assertions);
+    (state.session, result)
+}
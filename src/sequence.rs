@@ -0,0 +1,249 @@
+// src/sequence.rs
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use crate::types::{format_instance_tag, SEQUENCE_NONE};
+
+// 序列号范围 1..=65535，回绕到 1
+const SEQUENCE_SPAN: u32 = u16::MAX as u32;
+// 前向距离小于半个环视为"更新"，否则视为迟到
+pub const SEQUENCE_WINDOW: u32 = SEQUENCE_SPAN / 2;
+// 每个实例最多记录的缺失序列号，避免长时间断流后无限增长
+const MAX_TRACKED_MISSING: usize = 4096;
+
+// 下一个序列号 (跳过 0)
+pub fn next_sequence(sequence: u16) -> u16 {
+    if sequence == u16::MAX { 1 } else { sequence + 1 }
+}
+
+// 上一个序列号 (跳过 0)
+pub fn prev_sequence(sequence: u16) -> u16 {
+    if sequence <= 1 { u16::MAX } else { sequence - 1 }
+}
+
+// 在 1..=65535 的环上从 from 到 to 的前向距离
+pub fn forward_distance(from: u16, to: u16) -> u32 {
+    (u32::from(to) + SEQUENCE_SPAN - u32::from(from)) % SEQUENCE_SPAN
+}
+
+// 单帧检查结果
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SequenceEvent {
+    NotPresent,                                  // 帧中没有序列号
+    First,                                       // 该实例的第一帧
+    InOrder,
+    Gap { expected: u16, received: u16, missing: u32 },
+    Duplicate { sequence: u16 },
+    Reordered { sequence: u16 },                 // 迟到的帧，填补了之前的缺口
+}
+
+#[derive(Debug, Default)]
+struct InstanceSequence {
+    next_expected: u16,
+    missing: BTreeSet<u16>,
+    received: u64,
+    gaps: u64,
+    missing_total: u64,
+    duplicates: u64,
+    reordered: u64,
+}
+
+impl InstanceSequence {
+    fn check(&mut self, sequence: u16) -> SequenceEvent {
+        self.received += 1;
+
+        if self.next_expected == SEQUENCE_NONE {
+            self.next_expected = next_sequence(sequence);
+            return SequenceEvent::First;
+        }
+
+        let distance = forward_distance(self.next_expected, sequence);
+        if distance == 0 {
+            self.advance(sequence);
+            return SequenceEvent::InOrder;
+        }
+
+        if distance < SEQUENCE_WINDOW {
+            // 跳号：expected..sequence 之间的帧丢失
+            let expected = self.next_expected;
+            let mut missing_seq = expected;
+            while missing_seq != sequence {
+                if self.missing.len() < MAX_TRACKED_MISSING {
+                    self.missing.insert(missing_seq);
+                }
+                missing_seq = next_sequence(missing_seq);
+            }
+            self.gaps += 1;
+            self.missing_total += u64::from(distance);
+            self.advance(sequence);
+            return SequenceEvent::Gap { expected, received: sequence, missing: distance };
+        }
+
+        // 落后于期望值：填补缺口则为乱序，否则为重复
+        if self.missing.remove(&sequence) {
+            self.reordered += 1;
+            self.missing_total -= 1;
+            SequenceEvent::Reordered { sequence }
+        } else {
+            self.duplicates += 1;
+            SequenceEvent::Duplicate { sequence }
+        }
+    }
+
+    // 收到 sequence 后前移期望值，并清理已不可能再被填补的缺失记录
+    fn advance(&mut self, sequence: u16) {
+        self.next_expected = next_sequence(sequence);
+        // 上一轮遗留的同号缺失记录
+        self.missing.remove(&sequence);
+        // 位于 next_expected 之后半个环以内的序列号再到达时会被当作新帧，对应的记录已过期
+        let start = self.next_expected;
+        let end = u32::from(start) + SEQUENCE_WINDOW - 1;
+        let stale: Vec<u16> = if end <= SEQUENCE_SPAN {
+            self.missing.range(start..=end as u16).copied().collect()
+        } else {
+            self.missing.range(start..).chain(self.missing.range(1..=(end - SEQUENCE_SPAN) as u16)).copied().collect()
+        };
+        for sequence in stale {
+            self.missing.remove(&sequence);
+        }
+    }
+}
+
+// 按 instance_tag 跟踪序列号，检测丢包、重复和乱序
+#[derive(Debug, Default)]
+pub struct SequenceTracker {
+    instances: BTreeMap<[u8; 8], InstanceSequence>,
+}
+
+impl SequenceTracker {
+    pub fn new() -> Self {
+        SequenceTracker::default()
+    }
+
+    pub fn check(&mut self, instance_tag: &[u8; 8], sequence: u16) -> SequenceEvent {
+        if sequence == SEQUENCE_NONE {
+            return SequenceEvent::NotPresent;
+        }
+        self.instances.entry(*instance_tag).or_default().check(sequence)
+    }
+
+    // 会话结束时的序列号报告
+    pub fn print(&self) {
+        if self.instances.is_empty() {
+            return;
+        }
+        println!("Sequence by instance:");
+        for (tag, instance) in &self.instances {
+            println!("  Instance {}: Received={} | Gaps={} | Missing={} | Duplicates={} | Reordered={}",
+                format_instance_tag(tag), instance.received, instance.gaps, instance.missing_total,
+                instance.duplicates, instance.reordered);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TAG: [u8; 8] = *b"ENGINE-A";
+
+    #[test]
+    fn sequence_wraps_from_65535_to_1() {
+        assert_eq!(next_sequence(65534), 65535);
+        assert_eq!(next_sequence(65535), 1);
+        assert_eq!(prev_sequence(1), 65535);
+        assert_eq!(prev_sequence(2), 1);
+        assert_eq!(forward_distance(65535, 1), 1);
+        assert_eq!(forward_distance(65534, 2), 3);
+        assert_eq!(forward_distance(10, 10), 0);
+    }
+
+    #[test]
+    fn zero_means_no_sequence() {
+        let mut tracker = SequenceTracker::new();
+        assert_eq!(tracker.check(&TAG, SEQUENCE_NONE), SequenceEvent::NotPresent);
+        // 没有序列号的帧不建立实例状态
+        assert_eq!(tracker.check(&TAG, 7), SequenceEvent::First);
+        assert_eq!(tracker.check(&TAG, SEQUENCE_NONE), SequenceEvent::NotPresent);
+        assert_eq!(tracker.check(&TAG, 8), SequenceEvent::InOrder);
+    }
+
+    #[test]
+    fn in_order_across_wraparound() {
+        let mut tracker = SequenceTracker::new();
+        assert_eq!(tracker.check(&TAG, 65534), SequenceEvent::First);
+        assert_eq!(tracker.check(&TAG, 65535), SequenceEvent::InOrder);
+        assert_eq!(tracker.check(&TAG, 1), SequenceEvent::InOrder);
+        assert_eq!(tracker.check(&TAG, 2), SequenceEvent::InOrder);
+    }
+
+    #[test]
+    fn gap_then_late_frame_is_reordered() {
+        let mut tracker = SequenceTracker::new();
+        tracker.check(&TAG, 10);
+        assert_eq!(tracker.check(&TAG, 14), SequenceEvent::Gap { expected: 11, received: 14, missing: 3 });
+        assert_eq!(tracker.check(&TAG, 12), SequenceEvent::Reordered { sequence: 12 });
+        // 同一个缺口只能填一次
+        assert_eq!(tracker.check(&TAG, 12), SequenceEvent::Duplicate { sequence: 12 });
+        assert_eq!(tracker.check(&TAG, 14), SequenceEvent::Duplicate { sequence: 14 });
+        assert_eq!(tracker.check(&TAG, 15), SequenceEvent::InOrder);
+
+        let instance = &tracker.instances[&TAG];
+        assert_eq!(instance.gaps, 1);
+        assert_eq!(instance.missing_total, 2);
+        assert_eq!(instance.reordered, 1);
+        assert_eq!(instance.duplicates, 2);
+    }
+
+    #[test]
+    fn gap_across_wraparound() {
+        let mut tracker = SequenceTracker::new();
+        tracker.check(&TAG, 65533);
+        // 65534, 65535, 1 丢失 (0 不是有效序列号)
+        assert_eq!(tracker.check(&TAG, 2), SequenceEvent::Gap { expected: 65534, received: 2, missing: 3 });
+        assert_eq!(tracker.check(&TAG, 1), SequenceEvent::Reordered { sequence: 1 });
+        assert_eq!(tracker.check(&TAG, 3), SequenceEvent::InOrder);
+    }
+
+    #[test]
+    fn stale_missing_entries_expire_after_wraparound() {
+        let mut tracker = SequenceTracker::new();
+        tracker.check(&TAG, 10);
+        assert_eq!(tracker.check(&TAG, 12), SequenceEvent::Gap { expected: 11, received: 12, missing: 1 });
+        assert!(tracker.instances[&TAG].missing.contains(&11));
+
+        // 按序走完一整圈，11 落到期望值之后半个环以内时记录被清理
+        let mut sequence = 12;
+        while sequence != 10 {
+            sequence = next_sequence(sequence);
+            assert_eq!(tracker.check(&TAG, sequence), SequenceEvent::InOrder);
+        }
+        assert!(tracker.instances[&TAG].missing.is_empty());
+
+        // 新一轮的 11 按序到达，重复的 11 不能再算作乱序
+        assert_eq!(tracker.check(&TAG, 11), SequenceEvent::InOrder);
+        assert_eq!(tracker.check(&TAG, 11), SequenceEvent::Duplicate { sequence: 11 });
+        let instance = &tracker.instances[&TAG];
+        assert_eq!((instance.missing_total, instance.reordered, instance.duplicates), (1, 0, 1));
+    }
+
+    #[test]
+    fn in_order_frame_clears_its_missing_entry() {
+        let mut instance = InstanceSequence::default();
+        instance.check(100);
+        instance.missing.insert(101); // 上一轮遗留的记录
+        assert_eq!(instance.check(101), SequenceEvent::InOrder);
+        assert!(instance.missing.is_empty());
+        assert_eq!(instance.check(101), SequenceEvent::Duplicate { sequence: 101 });
+    }
+
+    #[test]
+    fn instances_are_tracked_separately() {
+        let mut tracker = SequenceTracker::new();
+        let other = *b"ENGINE-B";
+        tracker.check(&TAG, 100);
+        assert_eq!(tracker.check(&other, 5), SequenceEvent::First);
+        assert_eq!(tracker.check(&TAG, 101), SequenceEvent::InOrder);
+        assert_eq!(tracker.check(&other, 6), SequenceEvent::InOrder);
+    }
+}
@@ -0,0 +1,65 @@
+// src/stops.rs
+
+use crate::encoding::BroadcastMessage;
+use crate::types::{Order, ORDER_PRICE_TYPE_LIMIT, ORDER_PRICE_TYPE_MARKET, ORDER_PRICE_TYPE_MASK, ORDER_PRICE_TYPE_STOP, ORDER_TYPE_BUY};
+
+// 客户端合成的止损单：引擎不支持 STOP/STOP_LIMIT 时，由客户端按成交行情触发
+//   买入止损：成交价 >= 触发价；卖出止损：成交价 <= 触发价
+// 触发后按 MARKET (STOP) 或 LIMIT (STOP_LIMIT) 发出，挂起的止损单不跨进程保存
+#[derive(Debug, Default)]
+pub struct StopBook {
+    pending: Vec<Order>,
+}
+
+impl StopBook {
+    pub fn new() -> Self {
+        StopBook::default()
+    }
+
+    pub fn hold(&mut self, order: Order) {
+        self.pending.push(order);
+    }
+
+    pub fn len(&self) -> usize {
+        self.pending.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.pending.is_empty()
+    }
+
+    // 检查成交是否触发止损，返回需要发出的普通订单
+    pub fn check(&mut self, message: &BroadcastMessage, now: u64, triggered: &mut Vec<Order>) {
+        let BroadcastMessage::Trade(result) = message else {
+            return;
+        };
+
+        // 过期的止损单直接丢弃
+        self.pending.retain(|stop| stop.expire_time == 0 || stop.expire_time > now);
+
+        let mut index = 0;
+        while index < self.pending.len() {
+            let stop = &self.pending[index];
+            let hit = stop.product_id == result.product_id && if stop.order_type == ORDER_TYPE_BUY {
+                result.price >= stop.trigger_price
+            } else {
+                result.price <= stop.trigger_price
+            };
+            if !hit {
+                index += 1;
+                continue;
+            }
+
+            let mut order = self.pending.remove(index);
+            let base = if order.base_price_type() == ORDER_PRICE_TYPE_STOP {
+                ORDER_PRICE_TYPE_MARKET
+            } else {
+                ORDER_PRICE_TYPE_LIMIT
+            };
+            order.price_type = (order.price_type & !ORDER_PRICE_TYPE_MASK) | base;
+            order.trigger_price = 0;
+            order.submit_time = now;
+            triggered.push(order);
+        }
+    }
+}
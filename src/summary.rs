@@ -0,0 +1,86 @@
+// src/summary.rs
+
+use std::collections::BTreeMap;
+use std::time::Instant;
+
+use crate::encoding::BroadcastMessage;
+use crate::types::message_type_name;
+
+// 每个产品的成交统计
+#[derive(Debug, Default, Clone)]
+struct ProductActivity {
+    trades: u64,
+    volume: u64,
+}
+
+// 监听会话统计，Ctrl+C 退出时打印
+#[derive(Debug)]
+pub struct SessionSummary {
+    started: Instant,
+    frames_by_type: BTreeMap<u8, u64>, // 按 MSG_* 类型统计帧数
+    decode_errors: u64,
+    checksum_failures: u64,
+    dropped_sources: u64,              // 不在来源白名单中的帧
+    products: BTreeMap<u16, ProductActivity>,
+}
+
+impl SessionSummary {
+    pub fn new() -> Self {
+        SessionSummary {
+            started: Instant::now(),
+            frames_by_type: BTreeMap::new(),
+            decode_errors: 0,
+            checksum_failures: 0,
+            dropped_sources: 0,
+            products: BTreeMap::new(),
+        }
+    }
+
+    pub fn record_frame(&mut self, msg_type: u8) {
+        *self.frames_by_type.entry(msg_type).or_default() += 1;
+    }
+
+    pub fn record_checksum_failure(&mut self) {
+        self.checksum_failures += 1;
+    }
+
+    pub fn record_decode_error(&mut self) {
+        self.decode_errors += 1;
+    }
+
+    pub fn record_dropped_source(&mut self) {
+        self.dropped_sources += 1;
+    }
+
+    pub fn record_message(&mut self, message: &BroadcastMessage) {
+        if let BroadcastMessage::Trade(result) = message {
+            let activity = self.products.entry(result.product_id).or_default();
+            activity.trades += 1;
+            activity.volume += u64::from(result.quantity);
+        }
+    }
+
+    pub fn print(&self) {
+        let total: u64 = self.frames_by_type.values().sum();
+
+        println!("\n=============================================");
+        println!("Session Summary ({:.1}s)", self.started.elapsed().as_secs_f64());
+        println!("=============================================");
+        println!("Frames received: {}", total);
+        for (msg_type, count) in &self.frames_by_type {
+            println!("  {} ({}): {}", message_type_name(*msg_type), msg_type, count);
+        }
+        println!("Decode errors: {}", self.decode_errors);
+        println!("Checksum failures: {}", self.checksum_failures);
+        if self.dropped_sources > 0 {
+            println!("Dropped (source not allowed): {}", self.dropped_sources);
+        }
+        println!("Trades by product:");
+        if self.products.is_empty() {
+            println!("  (none)");
+        }
+        for (product_id, activity) in &self.products {
+            println!("  Product {}: Trades={} | Volume={}", product_id, activity.trades, activity.volume);
+        }
+    }
+}
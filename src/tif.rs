@@ -0,0 +1,165 @@
+// src/tif.rs
+
+use chrono::{DateTime, Local, NaiveTime, TimeZone};
+
+use crate::params::TimeInForce;
+use crate::types::{ORDER_TIF_FOK, ORDER_TIF_IOC};
+
+impl TimeInForce {
+    pub fn name(self) -> &'static str {
+        match self {
+            TimeInForce::Gtc => "GTC",
+            TimeInForce::Day => "DAY",
+            TimeInForce::Gtd => "GTD",
+            TimeInForce::Ioc => "IOC",
+            TimeInForce::Fok => "FOK",
+        }
+    }
+}
+
+// 解析收盘时间 HH:MM 或 HH:MM:SS (本地时间)
+pub fn parse_session_close(s: &str) -> Result<NaiveTime, String> {
+    NaiveTime::parse_from_str(s, "%H:%M:%S")
+        .or_else(|_| NaiveTime::parse_from_str(s, "%H:%M"))
+        .map_err(|_| format!("Invalid session close time: {}. Expected HH:MM or HH:MM:SS", s))
+}
+
+// 解析 RFC 3339 绝对时间 (如 2026-10-18T16:30:00+08:00)，返回 Unix 纳秒
+pub fn parse_rfc3339_nanos(s: &str) -> Result<u64, String> {
+    let time = DateTime::parse_from_rfc3339(s)
+        .map_err(|e| format!("Invalid RFC 3339 timestamp {}: {}", s, e))?;
+    time.timestamp_nanos_opt()
+        .and_then(|nanos| u64::try_from(nanos).ok())
+        .ok_or_else(|| format!("Timestamp out of range: {}", s))
+}
+
+// 纳秒时间戳按本地时区显示
+pub fn format_local_time(nanos: u64) -> String {
+    let time = Local.timestamp_nanos(nanos as i64);
+    time.format("%Y-%m-%d %H:%M:%S%.3f %:z").to_string()
+}
+
+// now (Unix 纳秒，取自 --clock) 所在本地日期的收盘时间，返回 Unix 纳秒
+fn session_close_today(close: NaiveTime, now: u64) -> Result<u64, String> {
+    let today = Local.timestamp_nanos(now as i64).date_naive().and_time(close);
+    let local = Local.from_local_datetime(&today)
+        .earliest()
+        .ok_or_else(|| format!("Session close {} does not exist in the local time zone today", close))?;
+    local.timestamp_nanos_opt()
+        .and_then(|nanos| u64::try_from(nanos).ok())
+        .ok_or_else(|| "Session close time out of range".to_string())
+}
+
+// 把 TIF 映射为 (expire_time, price_type 标志位)
+//   GTC     -> expire_time = 0 (兼容旧用法：--expire 秒数 > 0 时按 GTD 处理)
+//   DAY     -> now 所在日期的收盘时间
+//   GTD     -> --expire-at 绝对时间，或 --expire 相对秒数
+//   IOC/FOK -> expire_time = 0，price_type 高位置标志
+pub fn resolve_time_in_force(
+    tif: TimeInForce,
+    expire_at: Option<u64>,
+    expire_secs: u64,
+    session_close: NaiveTime,
+    now: u64,
+) -> Result<(u64, u8), String> {
+    let relative = || -> Result<u64, String> {
+        let expire_nanos = expire_secs.checked_mul(1_000_000_000)
+            .ok_or_else(|| "Expiration duration overflow".to_string())?;
+        now.checked_add(expire_nanos)
+            .ok_or_else(|| "Expiration time overflow".to_string())
+    };
+
+    if matches!(tif, TimeInForce::Day | TimeInForce::Ioc | TimeInForce::Fok)
+        && (expire_at.is_some() || expire_secs > 0) {
+        return Err(format!("--expire/--expire-at cannot be combined with {}", tif.name()));
+    }
+    if tif == TimeInForce::Gtc && expire_at.is_some() {
+        return Err("--expire-at requires --tif gtd".to_string());
+    }
+
+    let (expire_time, flags) = match tif {
+        TimeInForce::Gtc if expire_secs > 0 => (relative()?, 0),
+        TimeInForce::Gtc => (0, 0),
+        TimeInForce::Day => (session_close_today(session_close, now)?, 0),
+        TimeInForce::Gtd => match (expire_at, expire_secs) {
+            (Some(at), _) => (at, 0),
+            (None, secs) if secs > 0 => (relative()?, 0),
+            _ => return Err("GTD requires --expire-at <RFC 3339> or --expire <seconds>".to_string()),
+        },
+        TimeInForce::Ioc => (0, ORDER_TIF_IOC),
+        TimeInForce::Fok => (0, ORDER_TIF_FOK),
+    };
+
+    if expire_time != 0 && expire_time <= now {
+        return Err(format!("Expiry {} is not in the future", format_local_time(expire_time)));
+    }
+    Ok((expire_time, flags))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const NOW: u64 = 1_700_000_000_000_000_000;
+
+    fn close(s: &str) -> NaiveTime {
+        parse_session_close(s).unwrap()
+    }
+
+    #[test]
+    fn parses_rfc3339_with_offsets() {
+        assert_eq!(parse_rfc3339_nanos("1970-01-01T00:00:01Z"), Ok(1_000_000_000));
+        assert_eq!(parse_rfc3339_nanos("1970-01-01T08:00:01.5+08:00"), Ok(1_500_000_000));
+        assert!(parse_rfc3339_nanos("1969-12-31T23:59:59Z").unwrap_err().starts_with("Timestamp out of range"));
+        assert!(parse_rfc3339_nanos("2026-10-18 16:30").unwrap_err().starts_with("Invalid RFC 3339 timestamp"));
+    }
+
+    #[test]
+    fn parses_session_close() {
+        assert_eq!(close("16:30"), NaiveTime::from_hms_opt(16, 30, 0).unwrap());
+        assert_eq!(close("16:30:15"), NaiveTime::from_hms_opt(16, 30, 15).unwrap());
+        assert!(parse_session_close("25:00").is_err());
+        assert!(parse_session_close("4pm").is_err());
+    }
+
+    #[test]
+    fn gtc_ioc_and_fok() {
+        let gtc = resolve_time_in_force(TimeInForce::Gtc, None, 0, close("16:00"), NOW);
+        assert_eq!(gtc, Ok((0, 0)));
+        // 旧用法：GTC 加 --expire 秒数按 GTD 处理
+        assert_eq!(resolve_time_in_force(TimeInForce::Gtc, None, 5, close("16:00"), NOW), Ok((NOW + 5_000_000_000, 0)));
+        assert_eq!(resolve_time_in_force(TimeInForce::Ioc, None, 0, close("16:00"), NOW), Ok((0, ORDER_TIF_IOC)));
+        assert_eq!(resolve_time_in_force(TimeInForce::Fok, None, 0, close("16:00"), NOW), Ok((0, ORDER_TIF_FOK)));
+        assert!(resolve_time_in_force(TimeInForce::Ioc, None, 5, close("16:00"), NOW).is_err());
+        assert!(resolve_time_in_force(TimeInForce::Gtc, Some(NOW + 1), 0, close("16:00"), NOW).is_err());
+    }
+
+    #[test]
+    fn gtd_must_be_in_the_future() {
+        let gtd = |expire_at, expire_secs| resolve_time_in_force(TimeInForce::Gtd, expire_at, expire_secs, close("16:00"), NOW);
+        assert_eq!(gtd(Some(NOW + 1), 0), Ok((NOW + 1, 0)));
+        assert_eq!(gtd(None, 60), Ok((NOW + 60_000_000_000, 0)));
+        assert!(gtd(Some(NOW), 0).unwrap_err().ends_with("is not in the future"));
+        assert!(gtd(Some(NOW - 1), 0).unwrap_err().ends_with("is not in the future"));
+        assert!(gtd(None, 0).unwrap_err().starts_with("GTD requires"));
+        assert_eq!(gtd(None, u64::MAX), Err("Expiration duration overflow".to_string()));
+    }
+
+    // 本地时间 -> Unix 纳秒
+    fn local(year: i32, month: u32, day: u32, hour: u32) -> u64 {
+        Local.with_ymd_and_hms(year, month, day, hour, 0, 0).unwrap().timestamp_nanos_opt().unwrap() as u64
+    }
+
+    #[test]
+    fn day_expires_at_close_of_clock_date() {
+        let day = |session_close: &str, now| resolve_time_in_force(TimeInForce::Day, None, 0, close(session_close), now);
+
+        // 日期取自传入的 now，而不是运行测试的当天
+        assert_eq!(day("16:00", local(2025, 10, 18, 9)), Ok((local(2025, 10, 18, 16), 0)));
+        assert_eq!(day("16:00", local(2030, 1, 2, 15)), Ok((local(2030, 1, 2, 16), 0)));
+        // 收盘之后下 DAY 单
+        assert!(day("08:00", local(2025, 10, 18, 9)).unwrap_err().ends_with("is not in the future"));
+        assert!(day("09:00", local(2025, 10, 18, 9)).unwrap_err().ends_with("is not in the future"));
+        assert!(resolve_time_in_force(TimeInForce::Day, None, 5, close("16:00"), local(2025, 10, 18, 9)).is_err());
+    }
+}
@@ -0,0 +1,192 @@
+// src/transport.rs
+
+use std::fmt;
+use std::io::{self, Write};
+use std::net::{Ipv4Addr, SocketAddr, TcpStream, ToSocketAddrs, UdpSocket};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::network::{connect_tcp, create_multicast_listener, create_sender_socket, create_unicast_listener,
+    describe_socket_options, MulticastInterface, SocketOptions};
+
+// 发送端 Socket 可能与回复通道共享非阻塞标志 (见 GroupListener::add_reply_channel)，
+// 发送缓冲区满时按间隔重试，超时后报错
+const SEND_RETRY_INTERVAL: Duration = Duration::from_millis(1);
+const SEND_TIMEOUT: Duration = Duration::from_secs(5);
+
+// --- 传输方式 ---
+// mcast://  组播 UDP（默认，地址必须是组播地址）
+// udp://    单播 UDP（发送到引擎地址；监听时绑定本地登记端口）
+// tcp://    TCP 连接，按固定 MESSAGE_TOTAL_SIZE 字节帧收发
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Scheme {
+    Multicast,
+    Udp,
+    Tcp,
+}
+
+// 带传输方式的地址，例如 mcast://239.0.0.1:5000
+#[derive(Debug, Clone)]
+pub struct Endpoint {
+    pub scheme: Scheme,
+    pub addr: SocketAddr,
+}
+
+impl fmt::Display for Endpoint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let scheme = match self.scheme {
+            Scheme::Multicast => "mcast",
+            Scheme::Udp => "udp",
+            Scheme::Tcp => "tcp",
+        };
+        write!(f, "{}://{}", scheme, self.addr)
+    }
+}
+
+// 解析地址。未写 scheme 时按 IP 判断：组播地址为 mcast，否则为 udp
+pub fn parse_endpoint(s: &str) -> Result<Endpoint, String> {
+    let (scheme, rest) = match s.split_once("://") {
+        Some(("mcast", rest)) => (Some(Scheme::Multicast), rest),
+        Some(("udp", rest)) => (Some(Scheme::Udp), rest),
+        Some(("tcp", rest)) => (Some(Scheme::Tcp), rest),
+        Some((other, _)) => return Err(format!("Unknown transport scheme '{}' in '{}'. Must be mcast, udp or tcp", other, s)),
+        None => (None, s),
+    };
+
+    let addr = rest.to_socket_addrs()
+        .map_err(|e| format!("Invalid address '{}': {}", rest, e))?
+        .next()
+        .ok_or_else(|| format!("No address found for '{}'", rest))?;
+
+    let scheme = match scheme {
+        Some(Scheme::Multicast) if !addr.ip().is_multicast() => {
+            return Err(format!("Address {} is not a multicast address.", addr.ip()));
+        }
+        Some(scheme) => scheme,
+        None if addr.ip().is_multicast() => Scheme::Multicast,
+        None => Scheme::Udp,
+    };
+
+    Ok(Endpoint { scheme, addr })
+}
+
+// 发送端：组播/单播共用一个 UDP Socket，TCP 使用一个连接
+pub enum FrameSender {
+    Udp { socket: UdpSocket, target: SocketAddr },
+    Tcp(TcpStream),
+}
+
+impl FrameSender {
+    pub fn open(endpoint: &Endpoint, interface: &MulticastInterface, options: &SocketOptions) -> Result<Self, String> {
+        match endpoint.scheme {
+            Scheme::Multicast | Scheme::Udp => Ok(FrameSender::Udp {
+                socket: create_sender_socket(interface, options)?,
+                target: endpoint.addr,
+            }),
+            Scheme::Tcp => Ok(FrameSender::Tcp(connect_tcp(endpoint.addr, options)?)),
+        }
+    }
+
+    // 整帧发送：UDP 报文不可分割；TCP 遇到 WouldBlock 时继续发送未写完的部分，保持帧边界
+    pub fn send_frame(&mut self, frame: &[u8]) -> Result<(), String> {
+        let deadline = Instant::now() + SEND_TIMEOUT;
+        match self {
+            FrameSender::Udp { socket, target } => {
+                let sent = retry_would_block(deadline, || socket.send_to(frame, *target))
+                    .map_err(|e| format!("Failed to send message to {}: {}", target, e))?;
+                if sent != frame.len() {
+                    return Err(format!("Partial send: {} of {} bytes sent.", sent, frame.len()));
+                }
+                Ok(())
+            }
+            FrameSender::Tcp(stream) => {
+                let mut written = 0;
+                while written < frame.len() {
+                    match retry_would_block(deadline, || stream.write(&frame[written..])) {
+                        Ok(0) => return Err("Failed to send message over TCP: connection closed".to_string()),
+                        Ok(n) => written += n,
+                        Err(e) => return Err(format!("Failed to send message over TCP after {} of {} bytes: {}", written, frame.len(), e)),
+                    }
+                }
+                Ok(())
+            }
+        }
+    }
+
+    pub fn describe(&self) -> String {
+        match self {
+            FrameSender::Udp { socket, .. } => describe_socket_options(socket),
+            FrameSender::Tcp(stream) => describe_socket_options(stream),
+        }
+    }
+}
+
+// 执行一次 I/O；WouldBlock 时等待后重试直到 deadline，Interrupted 立即重试
+fn retry_would_block<T>(deadline: Instant, mut op: impl FnMut() -> io::Result<T>) -> io::Result<T> {
+    loop {
+        match op() {
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            Err(e) if e.kind() == io::ErrorKind::WouldBlock && Instant::now() < deadline => thread::sleep(SEND_RETRY_INTERVAL),
+            result => return result,
+        }
+    }
+}
+
+// 监听端：组播/单播 UDP 每个报文即一帧，TCP 需要按固定长度切分 (见 listener.rs)
+pub enum FrameListener {
+    Udp(UdpSocket),
+    Tcp(TcpStream),
+}
+
+impl FrameListener {
+    // source 仅对组播有效，用于源特定组播 (SSM) 加入
+    pub fn open(endpoint: &Endpoint, interface: &MulticastInterface, source: Option<Ipv4Addr>, options: &SocketOptions) -> Result<Self, String> {
+        if source.is_some() && endpoint.scheme != Scheme::Multicast {
+            return Err(format!("Source-specific join requires a mcast:// address, got {}", endpoint));
+        }
+
+        match endpoint.scheme {
+            Scheme::Multicast => Ok(FrameListener::Udp(create_multicast_listener(endpoint.addr, interface, source, options)?)),
+            Scheme::Udp => Ok(FrameListener::Udp(create_unicast_listener(endpoint.addr, options)?)),
+            Scheme::Tcp => Ok(FrameListener::Tcp(connect_tcp(endpoint.addr, options)?)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Read;
+    use std::net::TcpListener;
+
+    #[test]
+    fn non_blocking_tcp_sender_keeps_frame_boundaries() {
+        const FRAMES: usize = 200_000; // 10 MB，超过回环连接的收发缓冲区
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let reader = thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            // 先不读，让发送缓冲区写满
+            thread::sleep(Duration::from_millis(200));
+            let mut received = Vec::new();
+            stream.read_to_end(&mut received).unwrap();
+            received
+        });
+
+        // 与回复通道共享非阻塞标志的情形
+        let stream = TcpStream::connect(addr).unwrap();
+        stream.set_nonblocking(true).unwrap();
+        let mut sender = FrameSender::Tcp(stream);
+        for i in 0..FRAMES {
+            let frame = [(i % 251) as u8; 50];
+            sender.send_frame(&frame).unwrap();
+        }
+        drop(sender);
+
+        let received = reader.join().unwrap();
+        assert_eq!(received.len(), FRAMES * 50);
+        for (i, frame) in received.chunks(50).enumerate() {
+            assert!(frame.iter().all(|&b| b == (i % 251) as u8), "frame {} is corrupted", i);
+        }
+    }
+}
@@ -1,22 +1,132 @@
 // src/types.rs
 
-use std::time::{SystemTime, UNIX_EPOCH, Duration};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::encoding::calculate_checksum;
 
 // --- Message Type Constants ---
 pub const MSG_ORDER_SUBMIT: u8 = 1;      // Client -> Engine: Order submission
 pub const MSG_ORDER_CANCEL: u8 = 2;      // Client -> Engine: Order cancellation
+pub const MSG_RETRANSMIT_REQUEST: u8 = 3; // Client -> Engine/Replay server: Resend a sequence range
 pub const MSG_TRADE_BROADCAST: u8 = 10;  // Engine -> Client: Trade broadcast
 pub const MSG_STATUS_BROADCAST: u8 = 11; // Engine -> Client: Status broadcast
 
+// Human-readable name of a message type, e.g. for summaries and dumps
+pub fn message_type_name(msg_type: u8) -> &'static str {
+    match msg_type {
+        MSG_ORDER_SUBMIT => "MSG_ORDER_SUBMIT",
+        MSG_ORDER_CANCEL => "MSG_ORDER_CANCEL",
+        MSG_RETRANSMIT_REQUEST => "MSG_RETRANSMIT_REQUEST",
+        MSG_TRADE_BROADCAST => "MSG_TRADE_BROADCAST",
+        MSG_STATUS_BROADCAST => "MSG_STATUS_BROADCAST",
+        _ => "UNKNOWN",
+    }
+}
+
 // --- Order Type Constants ---
 pub const ORDER_TYPE_BUY: u8 = 1;          // Order side: Buy
 pub const ORDER_TYPE_SELL: u8 = 2;         // Order side: Sell
 pub const ORDER_PRICE_TYPE_LIMIT: u8 = 1;  // Order price type: Limit
 pub const ORDER_PRICE_TYPE_MARKET: u8 = 2; // Order price type: Market
+pub const ORDER_PRICE_TYPE_STOP: u8 = 3;       // Market order once trigger_price trades
+pub const ORDER_PRICE_TYPE_STOP_LIMIT: u8 = 4; // Limit order once trigger_price trades
+
+// --- Time-in-Force Flags ---
+// Carried in the high bits of price_type and ORed into the same byte as the base type
+// (LIMIT | IOC = 0x11), so engines must mask with ORDER_PRICE_TYPE_MASK to recover the
+// base type; reading the whole byte yields an unknown type. GTC/DAY/GTD are
+// expressed through expire_time (0 = GTC, otherwise an absolute deadline).
+pub const ORDER_PRICE_TYPE_MASK: u8 = 0x0F;
+pub const ORDER_TIF_IOC: u8 = 0x10; // Immediate-or-cancel: fill what is possible, cancel the rest
+pub const ORDER_TIF_FOK: u8 = 0x20; // Fill-or-kill: fill completely or not at all
+
+// --- Order Flags (also high bits of price_type) ---
+pub const ORDER_FLAG_POST_ONLY: u8 = 0x40; // Reject instead of taking liquidity
+pub const ORDER_FLAG_ICEBERG: u8 = 0x80;   // Show only display_quantity on the book
+
+// --- Submit Frame Extension ---
+// The Order payload ends at byte 42; the spare bytes 42..50 carry one extension
+// field selected by price_type: trigger_price (u64) for STOP/STOP_LIMIT, or
+// display_quantity (u32, bytes 42..46) for ICEBERG. The two cannot be combined.
+pub const ORDER_EXTENSION_OFFSET: usize = 42;
+
+// Display names of the base price types and the high-bit flags, shared by
+// command-line parsing, order listings and raw frame decoding.
+pub const ORDER_PRICE_TYPE_NAMES: [(u8, &str); 4] = [
+    (ORDER_PRICE_TYPE_LIMIT, "LIMIT"),
+    (ORDER_PRICE_TYPE_MARKET, "MARKET"),
+    (ORDER_PRICE_TYPE_STOP, "STOP"),
+    (ORDER_PRICE_TYPE_STOP_LIMIT, "STOP_LIMIT"),
+];
+pub const ORDER_FLAG_NAMES: [(u8, &str); 4] = [
+    (ORDER_TIF_IOC, "IOC"),
+    (ORDER_TIF_FOK, "FOK"),
+    (ORDER_FLAG_POST_ONLY, "POST_ONLY"),
+    (ORDER_FLAG_ICEBERG, "ICEBERG"),
+];
+
+// --- Field Offsets ---
+// Absolute byte offsets inside the 50-byte frame (byte 0 checksum, byte 1 message
+// type). encoding.rs reads and writes fields here and layout.rs annotates raw frames
+// from the same table, so the two cannot drift apart.
+pub const ORDER_PRODUCT_ID_OFFSET: usize = 2;   // u16
+pub const ORDER_ID_OFFSET: usize = 4;           // u64
+pub const ORDER_PRICE_OFFSET: usize = 12;       // u64
+pub const ORDER_QUANTITY_OFFSET: usize = 20;    // u32
+pub const ORDER_SIDE_OFFSET: usize = 24;        // u8, ORDER_TYPE_BUY / ORDER_TYPE_SELL
+pub const ORDER_PRICE_TYPE_OFFSET: usize = 25;  // u8, base type | flags
+pub const ORDER_SUBMIT_TIME_OFFSET: usize = 26; // u64 Unix nanos
+pub const ORDER_EXPIRE_TIME_OFFSET: usize = 34; // u64 Unix nanos, 0 = GTC
+
+pub const CANCEL_ORDER_ID_OFFSET: usize = 2; // u64
+
+pub const RETRANSMIT_INSTANCE_TAG_OFFSET: usize = 2;  // [u8; 8]
+pub const RETRANSMIT_START_SEQUENCE_OFFSET: usize = 10; // u16
+pub const RETRANSMIT_END_SEQUENCE_OFFSET: usize = 12;   // u16
+
+pub const TRADE_INSTANCE_TAG_OFFSET: usize = 2;          // [u8; 8]
+pub const TRADE_PRODUCT_ID_OFFSET: usize = 10;           // u16
+pub const TRADE_BUY_ORDER_ID_OFFSET: usize = 12;         // u64
+pub const TRADE_SELL_ORDER_ID_OFFSET: usize = 20;        // u64
+pub const TRADE_PRICE_OFFSET: usize = 28;                // u64
+pub const TRADE_QUANTITY_OFFSET: usize = 36;             // u32
+pub const TRADE_NETWORK_TIME_OFFSET: usize = 40;         // u32
+pub const TRADE_INTERNAL_MATCH_TIME_OFFSET: usize = 44;  // u32
+
+pub const STATS_INSTANCE_TAG_OFFSET: usize = 2;            // [u8; 8]
+pub const STATS_PRODUCT_ID_OFFSET: usize = 10;             // u16
+pub const STATS_BIDS_SIZE_OFFSET: usize = 12;              // u32
+pub const STATS_ASK_SIZE_OFFSET: usize = 16;               // u32
+pub const STATS_MATCHED_ORDERS_OFFSET: usize = 20;         // u32
+pub const STATS_TOTAL_RECEIVED_ORDERS_OFFSET: usize = 24;  // u32
+pub const STATS_START_TIME_OFFSET: usize = 28;             // u64 Unix nanos
 
 // --- Message Size Constant ---
 pub const MESSAGE_TOTAL_SIZE: usize = 50; // All network packets are 50 bytes fixed size.
 
+// --- Sequence Number Extension ---
+// Optional per-instance sequence number (u16, big-endian) in the last two bytes of
+// MSG_TRADE_BROADCAST / MSG_STATUS_BROADCAST frames. Both payloads end before this
+// offset (MatchResult at 48, BroadcastStats at 36), so older engines leave it zero.
+// Sequence numbers run 1..=65535 and wrap back to 1; 0 means "not present".
+pub const SEQUENCE_OFFSET: usize = 48;
+pub const SEQUENCE_NONE: u16 = 0;
+
+// Name of the base price type (flags in the high bits are ignored)
+pub fn price_type_name(price_type: u8) -> &'static str {
+    ORDER_PRICE_TYPE_NAMES.iter()
+        .find(|&&(value, _)| value == price_type & ORDER_PRICE_TYPE_MASK)
+        .map_or("UNKNOWN", |&(_, name)| name)
+}
+
+// Names of the flags set in the high bits of price_type
+pub fn price_type_flag_names(price_type: u8) -> Vec<&'static str> {
+    ORDER_FLAG_NAMES.iter()
+        .filter(|&&(flag, _)| price_type & flag != 0)
+        .map(|&(_, name)| name)
+        .collect()
+}
+
 // --- Data Structure Definitions ---
 
 // Order Structure (for MSG_ORDER_SUBMIT)
@@ -31,6 +141,67 @@ pub struct Order {
     pub submit_time: u64,   // Submission timestamp (Nanoseconds) (8 bytes)
     pub expire_time: u64,   // Expiration timestamp (Nanoseconds. 0 means GTC) (8 bytes)
     // Total Payload Size: 40 bytes
+    pub trigger_price: u64,    // STOP/STOP_LIMIT trigger at ORDER_EXTENSION_OFFSET (0 = none)
+    pub display_quantity: u32, // ICEBERG visible quantity at ORDER_EXTENSION_OFFSET (0 = none)
+}
+
+impl Order {
+    pub fn base_price_type(&self) -> u8 {
+        self.price_type & ORDER_PRICE_TYPE_MASK
+    }
+
+    pub fn is_market(&self) -> bool {
+        self.base_price_type() == ORDER_PRICE_TYPE_MARKET
+    }
+
+    pub fn is_stop(&self) -> bool {
+        matches!(self.base_price_type(), ORDER_PRICE_TYPE_STOP | ORDER_PRICE_TYPE_STOP_LIMIT)
+    }
+
+    // LIMIT and STOP_LIMIT carry a limit price; MARKET and STOP execute at market
+    pub fn has_limit_price(&self) -> bool {
+        matches!(self.base_price_type(), ORDER_PRICE_TYPE_LIMIT | ORDER_PRICE_TYPE_STOP_LIMIT)
+    }
+
+    pub fn is_iceberg(&self) -> bool {
+        self.price_type & ORDER_FLAG_ICEBERG != 0
+    }
+
+    pub fn is_post_only(&self) -> bool {
+        self.price_type & ORDER_FLAG_POST_ONLY != 0
+    }
+
+    pub fn price_type_name(&self) -> &'static str {
+        price_type_name(self.price_type)
+    }
+
+    // IOC/FOK orders never rest on the book
+    pub fn is_immediate(&self) -> bool {
+        self.price_type & (ORDER_TIF_IOC | ORDER_TIF_FOK) != 0
+    }
+
+    pub fn time_in_force_name(&self) -> &'static str {
+        if self.price_type & ORDER_TIF_FOK != 0 {
+            "FOK"
+        } else if self.price_type & ORDER_TIF_IOC != 0 {
+            "IOC"
+        } else if self.expire_time == 0 {
+            "GTC"
+        } else {
+            "GTD"
+        }
+    }
+}
+
+// Retransmission Request Structure (for MSG_RETRANSMIT_REQUEST)
+// Asks the engine (or a replay server) to resend broadcast frames of one
+// instance whose sequence numbers fall in start_sequence..=end_sequence.
+#[derive(Debug, Clone)]
+pub struct RetransmitRequest {
+    pub instance_tag: [u8; 8], // Engine instance whose frames are missing (8 bytes)
+    pub start_sequence: u16,   // First missing sequence number (2 bytes)
+    pub end_sequence: u16,     // Last missing sequence number, inclusive (2 bytes)
+    // Total Payload Size: 12 bytes
 }
 
 // 获取自 Unix Epoch (1970-01-01) 以来的纳秒数
@@ -42,53 +213,27 @@ pub fn get_nanos_since_epoch() -> Result<u64, String> {
 }
 
 
+#[allow(dead_code)] // Engine-side encoder, kept alongside the decoder for tooling
 pub fn serialize_stats_result(stats: &BroadcastStats) -> [u8; MESSAGE_TOTAL_SIZE] {
     let mut buf = [0u8; MESSAGE_TOTAL_SIZE];
 
-    // Payload starts after Checksum (1 byte) and Message Type (1 byte)
-    let payload_start_idx = 2;
-    let mut current_idx = payload_start_idx;
-
-    // Assuming MSG_STATUS_BROADCAST and calculate_checksum are defined elsewhere
     buf[1] = MSG_STATUS_BROADCAST;
 
-    // --- Payload Serialization (Total 30 bytes) ---
-
-    // 1. Instance Tag ([u8; 8])
-    // Size: 8 bytes
-    buf[current_idx..current_idx + 8].copy_from_slice(&stats.instance_tag);
-    current_idx += 8; // Index: 10
-
-    // 2. Product ID (u16)
-    // Size: 2 bytes
-    buf[current_idx..current_idx + 2].copy_from_slice(&stats.product_id.to_be_bytes());
-    current_idx += 2; // Index: 12
-
-    // 3. Order Book Size (u32)
-    // Size: 4 bytes (FIXED from u64)
-    buf[current_idx..current_idx + 4].copy_from_slice(&stats.bids_size.to_be_bytes());
-    current_idx += 4; // Index: 16
-
-    buf[current_idx..current_idx + 4].copy_from_slice(&stats.ask_size.to_be_bytes());
-    current_idx += 4; // Index: 16
+    // --- Payload Serialization (Total 34 bytes, see STATS_*_OFFSET) ---
+    buf[STATS_INSTANCE_TAG_OFFSET..STATS_INSTANCE_TAG_OFFSET + 8].copy_from_slice(&stats.instance_tag);
+    buf[STATS_PRODUCT_ID_OFFSET..STATS_PRODUCT_ID_OFFSET + 2].copy_from_slice(&stats.product_id.to_be_bytes());
+    buf[STATS_BIDS_SIZE_OFFSET..STATS_BIDS_SIZE_OFFSET + 4].copy_from_slice(&stats.bids_size.to_be_bytes());
+    buf[STATS_ASK_SIZE_OFFSET..STATS_ASK_SIZE_OFFSET + 4].copy_from_slice(&stats.ask_size.to_be_bytes());
+    buf[STATS_MATCHED_ORDERS_OFFSET..STATS_MATCHED_ORDERS_OFFSET + 4].copy_from_slice(&stats.matched_orders.to_be_bytes());
+    buf[STATS_TOTAL_RECEIVED_ORDERS_OFFSET..STATS_TOTAL_RECEIVED_ORDERS_OFFSET + 4]
+        .copy_from_slice(&stats.total_received_orders.to_be_bytes());
+    buf[STATS_START_TIME_OFFSET..STATS_START_TIME_OFFSET + 8].copy_from_slice(&stats.start_time.to_be_bytes());
 
-    // 4. Matched Orders (u32)
-    // Size: 4 bytes (FIXED from u64)
-    buf[current_idx..current_idx + 4].copy_from_slice(&stats.matched_orders.to_be_bytes());
-    current_idx += 4; // Index: 20
-
-    // 5. Total Received Orders (u32)
-    // Size: 4 bytes (FIXED from u64)
-    buf[current_idx..current_idx + 4].copy_from_slice(&stats.total_received_orders.to_be_bytes());
-    current_idx += 4; // Index: 24
-
-    // 6. Start Time (u64)
-    // Size: 8 bytes
-    buf[current_idx..current_idx + 8].copy_from_slice(&stats.start_time.to_be_bytes());
-    current_idx += 8; // Index: 32 (Last index written: 31)
+    // Sequence Number (u16) in the tail bytes, 0 when not used
+    buf[SEQUENCE_OFFSET..SEQUENCE_OFFSET + 2].copy_from_slice(&stats.sequence.to_be_bytes());
 
     // Checksum calculation and placement
-    // Last data byte is at index 31. Padding goes from index 32 up to MESSAGE_TOTAL_SIZE - 1.
+    // Padding runs from the end of start_time up to SEQUENCE_OFFSET - 1.
     buf[0] = calculate_checksum(&buf);
 
     buf
@@ -104,6 +249,7 @@ pub struct BroadcastStats {
     pub total_received_orders: u32, // Total received orders count (4 bytes)
     pub start_time: u64,            // Program start time (Nanoseconds) (8 bytes)
                                     // Total Payload Size: 42 bytes
+    pub sequence: u16,              // Per-instance sequence number at SEQUENCE_OFFSET (0 = none)
 }
 
 // Match Result Structure (for MSG_TRADE_BROADCAST)
@@ -117,10 +263,39 @@ pub struct MatchResult {
     pub quantity: u32,            // Trade quantity (4 bytes)
     pub trade_network_time: u32,  // Trade timestamp (Nanoseconds) (8 bytes)
     pub internal_match_time: u32, // Total Payload Size: 46 bytes
+    pub sequence: u16,            // Per-instance sequence number at SEQUENCE_OFFSET (0 = none)
 }
 
+// Engine instance tags are usually short ASCII names padded with NULs;
+// fall back to hex when the tag is not printable.
+pub fn format_instance_tag(tag: &[u8; 8]) -> String {
+    let trimmed: &[u8] = match tag.iter().rposition(|&b| b != 0) {
+        Some(last) => &tag[..=last],
+        None => &[],
+    };
+    if !trimmed.is_empty() && trimmed.iter().all(|b| b.is_ascii_graphic()) {
+        String::from_utf8_lossy(trimmed).into_owned()
+    } else {
+        format!("0x{}", tag.iter().map(|b| format!("{:02x}", b)).collect::<String>())
+    }
+}
 
-fn calculate_checksum(buf: &[u8]) -> u8 {
-    // Checksum is calculated over the payload (index 2 onwards)
-    buf[2..].iter().fold(0, |acc, &x| acc ^ x)
-}
\ No newline at end of file
+// Inverse of format_instance_tag: "0x" + 16 hex digits, or up to 8 ASCII bytes (NUL padded)
+pub fn parse_instance_tag(s: &str) -> Result<[u8; 8], String> {
+    let mut tag = [0u8; 8];
+    if let Some(hex) = s.strip_prefix("0x") {
+        if hex.len() != 16 {
+            return Err(format!("Hex instance tag must have 16 digits: {}", s));
+        }
+        for (i, byte) in tag.iter_mut().enumerate() {
+            *byte = u8::from_str_radix(&hex[i * 2..i * 2 + 2], 16)
+                .map_err(|_| format!("Invalid hex instance tag: {}", s))?;
+        }
+    } else {
+        if s.is_empty() || s.len() > 8 || !s.is_ascii() {
+            return Err(format!("Instance tag must be 1-8 ASCII characters: {}", s));
+        }
+        tag[..s.len()].copy_from_slice(s.as_bytes());
+    }
+    Ok(tag)
+}